@@ -7,6 +7,7 @@ pub fn generate_ast(output_dir: &str) -> io::Result<()>{
         "Binary   > left: Box<Expr>, operator: Token, right: Box<Expr>".to_owned(),
         "Call     > callee: Box<Expr>, paren: Token, arguments: Vec<Expr>".to_owned(),
         "Grouping > expression: Box<Expr>".to_owned(),
+        "Interpolation > parts: Vec<Expr>".to_owned(),
         "Literal  > value: Option<Object>".to_owned(),
         "Logical  > left: Box<Expr>, operator: Token, right: Box<Expr>".to_owned(),
         "Unary    > operator: Token, right: Box<Expr>".to_owned(),
@@ -19,7 +20,7 @@ pub fn generate_ast(output_dir: &str) -> io::Result<()>{
         "Expression > expression: Expr".to_owned(),
         "Function   > name: Token, parameters: Rc<Vec<Token>>, body: Rc<Vec<Stmt>>".to_owned(),
         "If         > condition: Expr, then_branch: Box<Stmt>, else_branch: Option<Box<Stmt>>".to_owned(),
-        "Print      > expression: Expr".to_owned(),
+        "Print      > keyword: Token, expression: Expr".to_owned(),
         "Return     > keyword: Token, value: Expr".to_owned(),
         "Var        > name: Token, initializer: Option<Expr>".to_owned(),
         "While      > condition: Expr, body: Box<Stmt>".to_owned(),
@@ -127,6 +128,7 @@ fn define_visitor(file: &mut fs::File, base_name: &str, types: &[String], assoty
 }
 
 fn define_enum(file: &mut fs::File, base_name: &str, types: &[String]) -> io::Result<()>{
+    writeln!(file, "#[derive(Clone)]")?;
     writeln!(file, "pub enum {base_name} {{")?;
     for ttype in types {
         let class_name = ttype.split('>').next().unwrap().trim();
@@ -1,42 +1,166 @@
-use crate::{expr::*, error::LoxError};
+use std::rc::Rc;
 
-pub struct AstPrinter {}
+use crate::{expr::*, stmt::*, error::LoxError, object::Object};
+
+#[derive(Clone, Copy)]
+pub enum AstFormat {
+    SExpr,  // (while (< a 10) (block (print a)))
+    Tree,   // one node per line, children indented below their parent
+}
+
+pub struct AstPrinter {
+    format: AstFormat,
+    depth: usize,
+}
+
+enum Child<'a> {
+    Expr(&'a Expr),
+    Stmt(&'a Stmt),
+}
 
 impl ExprVisitor for AstPrinter {
     type Output = String;
-    fn visit_binary_expr(&self, expr: &BinaryExpr) -> Result<Self::Output, LoxError> {
-        self.parenthesize(&expr.operator.lexeme, vec![&expr.left, &expr.right])
+
+    fn visit_assign_expr(&mut self, expr: Rc<AssignExpr>) -> Result<Self::Output, LoxError> {
+        self.node(&format!("= {}", expr.name.lexeme), &[Child::Expr(&expr.value)])
+    }
+
+    fn visit_binary_expr(&mut self, expr: Rc<BinaryExpr>) -> Result<Self::Output, LoxError> {
+        self.node(&expr.operator.lexeme, &[Child::Expr(&expr.left), Child::Expr(&expr.right)])
+    }
+
+    fn visit_call_expr(&mut self, expr: Rc<CallExpr>) -> Result<Self::Output, LoxError> {
+        let mut children = vec![Child::Expr(&expr.callee)];
+        children.extend(expr.arguments.iter().map(Child::Expr));
+        self.node("call", &children)
+    }
+
+    fn visit_grouping_expr(&mut self, expr: Rc<GroupingExpr>) -> Result<Self::Output, LoxError> {
+        self.node("group", &[Child::Expr(&expr.expression)])
     }
 
-    fn visit_grouping_expr(&self, expr: &GroupingExpr) -> Result<Self::Output, LoxError> {
-        self.parenthesize("group", vec![&expr.expression])
+    fn visit_interpolation_expr(&mut self, expr: Rc<InterpolationExpr>) -> Result<Self::Output, LoxError> {
+        let children = expr.parts.iter().map(Child::Expr).collect::<Vec<_>>();
+        self.node("interpolate", &children)
     }
 
-    fn visit_literal_expr(&self, expr: &LiteralExpr) -> Result<Self::Output, LoxError> {
+    fn visit_literal_expr(&mut self, expr: Rc<LiteralExpr>) -> Result<Self::Output, LoxError> {
         match &expr.value {
-            None => Ok("nil".to_owned()),
-            Some(value) => Ok(value.to_string())
+            Some(Object::Str(value)) => self.leaf(&format!("{value:?}")),     // escaped, so each string stays on one line
+            Some(value) => self.leaf(&value.to_string()),
+            None => self.leaf("nil"),
         }
     }
 
-    fn visit_unary_expr(&self, expr: &UnaryExpr) -> Result<Self::Output, LoxError> {
-        self.parenthesize(&expr.operator.lexeme, vec![&expr.right])
+    fn visit_logical_expr(&mut self, expr: Rc<LogicalExpr>) -> Result<Self::Output, LoxError> {
+        self.node(&expr.operator.lexeme, &[Child::Expr(&expr.left), Child::Expr(&expr.right)])
+    }
+
+    fn visit_unary_expr(&mut self, expr: Rc<UnaryExpr>) -> Result<Self::Output, LoxError> {
+        self.node(&expr.operator.lexeme, &[Child::Expr(&expr.right)])
+    }
+
+    fn visit_variable_expr(&mut self, expr: Rc<VariableExpr>) -> Result<Self::Output, LoxError> {
+        self.leaf(&expr.name.lexeme)
+    }
+}
+
+impl StmtVisitor for AstPrinter {
+    type Output = String;
+
+    fn visit_break_stmt(&mut self, _stmt: Rc<BreakStmt>) -> Result<Self::Output, LoxError> {
+        self.node("break", &[])
+    }
+
+    fn visit_block_stmt(&mut self, stmt: Rc<BlockStmt>) -> Result<Self::Output, LoxError> {
+        let children: Vec<Child> = stmt.statements.iter().map(Child::Stmt).collect();
+        self.node("block", &children)
+    }
+
+    fn visit_expression_stmt(&mut self, stmt: Rc<ExpressionStmt>) -> Result<Self::Output, LoxError> {
+        self.node(";", &[Child::Expr(&stmt.expression)])
+    }
+
+    fn visit_function_stmt(&mut self, stmt: Rc<FunctionStmt>) -> Result<Self::Output, LoxError> {
+        let parameters: Vec<&str> = stmt.parameters.iter().map(|param| param.lexeme.as_str()).collect();
+        let children: Vec<Child> = stmt.body.iter().map(Child::Stmt).collect();
+        self.node(&format!("fun {}({})", stmt.name.lexeme, parameters.join(" ")), &children)
+    }
+
+    fn visit_if_stmt(&mut self, stmt: Rc<IfStmt>) -> Result<Self::Output, LoxError> {
+        let mut children = vec![Child::Expr(&stmt.condition), Child::Stmt(&stmt.then_branch)];
+        if let Some(else_branch) = &stmt.else_branch {
+            children.push(Child::Stmt(else_branch));
+        }
+        self.node("if", &children)
+    }
+
+    fn visit_print_stmt(&mut self, stmt: Rc<PrintStmt>) -> Result<Self::Output, LoxError> {
+        self.node("print", &[Child::Expr(&stmt.expression)])
+    }
+
+    fn visit_return_stmt(&mut self, stmt: Rc<ReturnStmt>) -> Result<Self::Output, LoxError> {
+        self.node("return", &[Child::Expr(&stmt.value)])
+    }
+
+    fn visit_var_stmt(&mut self, stmt: Rc<VarStmt>) -> Result<Self::Output, LoxError> {
+        match &stmt.initializer {
+            Some(initializer) => self.node(&format!("var {} =", stmt.name.lexeme), &[Child::Expr(initializer)]),
+            None => self.node(&format!("var {}", stmt.name.lexeme), &[]),
+        }
+    }
+
+    fn visit_while_stmt(&mut self, stmt: Rc<WhileStmt>) -> Result<Self::Output, LoxError> {
+        self.node("while", &[Child::Expr(&stmt.condition), Child::Stmt(&stmt.body)])
     }
 }
 
 impl AstPrinter {
-    pub fn print(&self, expr: &Expr) -> Result<String, LoxError> {
-        expr.accept(self)
+    pub fn new(format: AstFormat) -> Self {
+        Self { format, depth: 0 }
+    }
+
+    pub fn print(&mut self, statements: &[Stmt]) -> Result<String, LoxError> {
+        let mut builder = String::new();
+        for stmt in statements {
+            builder.push_str(&stmt.accept(self)?);
+            if let AstFormat::SExpr = self.format {
+                builder.push('\n');
+            }
+        }
+        Ok(builder)
+    }
+
+    fn leaf(&self, name: &str) -> Result<String, LoxError> {
+        match self.format {
+            AstFormat::SExpr => Ok(name.to_owned()),
+            AstFormat::Tree => Ok(format!("{}{name}\n", "  ".repeat(self.depth))),
+        }
     }
 
-    fn parenthesize(&self, name: &str, exprs: Vec<&Expr>) -> Result<String, LoxError> {
-        let mut builder = format!("({name}");
+    fn node(&mut self, name: &str, children: &[Child]) -> Result<String, LoxError> {
+        let mut builder = match self.format {
+            AstFormat::SExpr => format!("({name}"),
+            AstFormat::Tree => self.leaf(name)?,
+        };
+
+        self.depth += 1;
+        let rendered = children.iter().map(|child| match child {
+            Child::Expr(expr) => expr.accept(self),
+            Child::Stmt(stmt) => stmt.accept(self),
+        }).collect::<Result<Vec<_>, _>>();
+        self.depth -= 1;
 
-        for expr in exprs {
-            builder.push_str(&format!(" {}", expr.accept(self)?));
+        for child in rendered? {
+            match self.format {
+                AstFormat::SExpr => builder.push_str(&format!(" {child}")),
+                AstFormat::Tree => builder.push_str(&child),
+            }
         }
-        builder.push(')');
 
+        if let AstFormat::SExpr = self.format {
+            builder.push(')');
+        }
         Ok(builder)
-    } 
-}
\ No newline at end of file
+    }
+}
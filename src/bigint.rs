@@ -0,0 +1,514 @@
+use std::{cmp::Ordering, fmt};
+
+// An integer of any size: a sign and a magnitude in base 2^32, least significant digit first.
+// The magnitude never has leading zeros, and zero is never negative.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BigInt {
+    negative: bool,
+    digits: Vec<u32>,
+}
+
+const BASE: u64 = 1 << 32;
+const DECIMAL_CHUNK: u32 = 1_000_000_000;     // the largest power of ten below BASE
+
+impl BigInt {
+    pub fn zero() -> Self {
+        Self { negative: false, digits: Vec::new() }
+    }
+
+    fn new(negative: bool, mut digits: Vec<u32>) -> Self {
+        while digits.last() == Some(&0) {
+            digits.pop();
+        }
+        Self { negative: negative && !digits.is_empty(), digits }
+    }
+
+    pub fn is_zero(&self) -> bool {
+        self.digits.is_empty()
+    }
+
+    pub fn is_negative(&self) -> bool {
+        self.negative
+    }
+
+    pub fn is_odd(&self) -> bool {
+        self.digits.first().is_some_and(|digit| digit & 1 == 1)
+    }
+
+    pub fn abs(&self) -> Self {
+        Self { negative: false, digits: self.digits.clone() }
+    }
+
+    pub fn to_i64(&self) -> Option<i64> {
+        if self.digits.len() > 2 {
+            return None;
+        }
+        let magnitude = self.digits.iter().rev().fold(0u64, |acc, digit| acc << 32 | *digit as u64);
+        match self.negative {
+            true if magnitude <= i64::MIN.unsigned_abs() => Some((magnitude as i64).wrapping_neg()),
+            false if magnitude <= i64::MAX as u64 => Some(magnitude as i64),
+            _ => None,
+        }
+    }
+
+    // The nearest float, ties to even, and infinity beyond the largest. The top 64 bits, with the
+    // last one set if any bit below them is, round just as the whole magnitude would.
+    pub fn to_f64(&self) -> f64 {
+        let shift = self.bits().saturating_sub(64);
+        let first = (shift / 32) as usize;
+        let top = self.digits[first.min(self.digits.len())..].iter().take(3).rev()
+            .fold(0u128, |acc, digit| acc << 32 | *digit as u128) >> (shift % 32);
+        let mut value = (top as u64 | self.any_below(shift) as u64) as f64;
+
+        // Scaled in steps, as 2^shift alone may not fit in a float.
+        let mut shift = shift;
+        while shift != 0 && value.is_finite() {
+            let step = shift.min(1000);
+            value *= 2f64.powi(step as i32);
+            shift -= step;
+        }
+        if self.negative { -value } else { value }
+    }
+
+    // Decimal digits with an optional leading `-`; `None` for anything else.
+    pub fn parse(text: &str) -> Option<Self> {
+        let (negative, digits) = match text.strip_prefix('-') {
+            Some(digits) => (true, digits),
+            None => (false, text),
+        };
+        if digits.is_empty() || !digits.bytes().all(|byte| byte.is_ascii_digit()) {
+            return None;
+        }
+
+        let mut value = Self::zero();
+        for chunk in digits.as_bytes().chunks(9) {
+            let chunk = std::str::from_utf8(chunk).ok()?;
+            value = value.mul_small(10u32.pow(chunk.len() as u32)).add_small(chunk.parse().ok()?);
+        }
+        value.negative = negative && !value.is_zero();
+        Some(value)
+    }
+
+    // Digits of any radix up to 36, without a sign; `None` for anything else.
+    pub fn parse_radix(digits: &str, radix: u32) -> Option<Self> {
+        if digits.is_empty() {
+            return None;
+        }
+        digits.chars().try_fold(Self::zero(), |value, digit| Some(value.mul_small(radix).add_small(digit.to_digit(radix)?)))
+    }
+
+    pub fn bits(&self) -> u64 {
+        match self.digits.last() {
+            Some(top) => self.digits.len() as u64 * 32 - top.leading_zeros() as u64,
+            None => 0,
+        }
+    }
+
+    fn bit(&self, index: u64) -> bool {
+        self.digits.get((index / 32) as usize).is_some_and(|digit| digit >> (index % 32) & 1 == 1)
+    }
+
+    // Whether any of the lowest `bits` bits of the magnitude is set.
+    fn any_below(&self, bits: u64) -> bool {
+        let whole = ((bits / 32) as usize).min(self.digits.len());
+        self.digits[..whole].iter().any(|digit| *digit != 0)
+            || self.digits.get(whole).is_some_and(|digit| *digit as u64 & ((1 << (bits % 32)) - 1) != 0)
+    }
+
+    pub fn shl(&self, bits: u64) -> Self {
+        let mut digits = vec![0; (bits / 32) as usize];
+        let shift = bits % 32;
+        let mut carry = 0;
+        for digit in &self.digits {
+            let wide = (*digit as u64) << shift | carry;
+            digits.push(wide as u32);
+            carry = wide >> 32;
+        }
+        digits.push(carry as u32);
+        Self::new(self.negative, digits)
+    }
+
+    // Rounded toward negative infinity, as `>>` on a two's complement integer would.
+    pub fn shr(&self, bits: u64) -> Self {
+        match bits >= self.bits() {
+            true if self.negative => Self::from(-1),
+            true => Self::zero(),
+            false => {
+                let shift = bits % 32;
+                let digits = &self.digits[(bits / 32) as usize..];
+                let shifted = (0..digits.len())
+                    .map(|index| {
+                        let high = digits.get(index + 1).map_or(0, |digit| *digit as u64) << 32;
+                        ((high | digits[index] as u64) >> shift) as u32
+                    })
+                    .collect();
+                let quotient = Self::new(self.negative, shifted);
+                match self.negative && self.any_below(bits) {
+                    true => quotient.sub(&Self::from(1)),
+                    false => quotient,
+                }
+            },
+        }
+    }
+
+    // `&`, `|` and `^` digit by digit, as if both were two's complement with endless sign bits.
+    pub fn bitwise(&self, rhs: &Self, operation: fn(u32, u32) -> u32) -> Self {
+        let length = self.digits.len().max(rhs.digits.len()) + 1;
+        let (a, b) = (self.twos_complement(length), rhs.twos_complement(length));
+        Self::from_twos_complement(a.iter().zip(&b).map(|(a, b)| operation(*a, *b)).collect())
+    }
+
+    // `length` must leave room for the sign bit.
+    fn twos_complement(&self, length: usize) -> Vec<u32> {
+        let mut digits = self.digits.clone();
+        digits.resize(length, 0);
+        if self.negative {
+            Self::negate_digits(&mut digits);
+        }
+        digits
+    }
+
+    fn from_twos_complement(mut digits: Vec<u32>) -> Self {
+        let negative = digits.last().is_some_and(|top| top >> 31 == 1);
+        if negative {
+            Self::negate_digits(&mut digits);
+        }
+        Self::new(negative, digits)
+    }
+
+    // Inverts and adds one, in place.
+    fn negate_digits(digits: &mut [u32]) {
+        let mut carry = 1;
+        for digit in digits.iter_mut() {
+            let sum = (!*digit) as u64 + carry;
+            *digit = sum as u32;
+            carry = sum >> 32;
+        }
+    }
+
+    fn add_small(mut self, value: u32) -> Self {
+        let mut carry = value as u64;
+        for digit in self.digits.iter_mut() {
+            if carry == 0 {
+                break;
+            }
+            let sum = *digit as u64 + carry;
+            *digit = sum as u32;
+            carry = sum >> 32;
+        }
+        if carry > 0 {
+            self.digits.push(carry as u32);
+        }
+        self
+    }
+
+    fn mul_small(mut self, value: u32) -> Self {
+        let mut carry = 0;
+        for digit in self.digits.iter_mut() {
+            let product = *digit as u64 * value as u64 + carry;
+            *digit = product as u32;
+            carry = product >> 32;
+        }
+        self.digits.push(carry as u32);
+        Self::new(self.negative, self.digits)
+    }
+
+    // Divides the magnitude in place and returns the remainder.
+    fn div_small(&mut self, value: u32) -> u32 {
+        let mut remainder = 0;
+        for digit in self.digits.iter_mut().rev() {
+            let wide = remainder << 32 | *digit as u64;
+            *digit = (wide / value as u64) as u32;
+            remainder = wide % value as u64;
+        }
+        *self = Self::new(self.negative, std::mem::take(&mut self.digits));
+        remainder as u32
+    }
+
+    fn cmp_magnitude(a: &[u32], b: &[u32]) -> Ordering {
+        a.len().cmp(&b.len()).then_with(|| a.iter().rev().cmp(b.iter().rev()))
+    }
+
+    fn add_magnitude(a: &[u32], b: &[u32]) -> Vec<u32> {
+        let (long, short) = if a.len() >= b.len() { (a, b) } else { (b, a) };
+        let mut digits = Vec::with_capacity(long.len() + 1);
+        let mut carry = 0;
+        for (i, digit) in long.iter().enumerate() {
+            let sum = *digit as u64 + short.get(i).copied().unwrap_or(0) as u64 + carry;
+            digits.push(sum as u32);
+            carry = sum >> 32;
+        }
+        digits.push(carry as u32);
+        digits
+    }
+
+    // `a` must not be smaller than `b`.
+    fn sub_magnitude(a: &[u32], b: &[u32]) -> Vec<u32> {
+        let mut digits = Vec::with_capacity(a.len());
+        let mut borrow = 0;
+        for (i, digit) in a.iter().enumerate() {
+            let difference = *digit as i64 - b.get(i).copied().unwrap_or(0) as i64 - borrow;
+            borrow = i64::from(difference < 0);
+            digits.push((difference + borrow * BASE as i64) as u32);
+        }
+        digits
+    }
+
+    pub fn neg(&self) -> Self {
+        Self::new(!self.negative, self.digits.clone())
+    }
+
+    pub fn add(&self, rhs: &Self) -> Self {
+        if self.negative == rhs.negative {
+            return Self::new(self.negative, Self::add_magnitude(&self.digits, &rhs.digits));
+        }
+        match Self::cmp_magnitude(&self.digits, &rhs.digits) {
+            Ordering::Less => Self::new(rhs.negative, Self::sub_magnitude(&rhs.digits, &self.digits)),
+            _ => Self::new(self.negative, Self::sub_magnitude(&self.digits, &rhs.digits)),
+        }
+    }
+
+    pub fn sub(&self, rhs: &Self) -> Self {
+        self.add(&rhs.neg())
+    }
+
+    pub fn mul(&self, rhs: &Self) -> Self {
+        let mut digits = vec![0u32; self.digits.len() + rhs.digits.len()];
+        for (i, a) in self.digits.iter().enumerate() {
+            let mut carry = 0;
+            for (j, b) in rhs.digits.iter().enumerate() {
+                let product = *a as u64 * *b as u64 + digits[i + j] as u64 + carry;
+                digits[i + j] = product as u32;
+                carry = product >> 32;
+            }
+            digits[i + rhs.digits.len()] = carry as u32;
+        }
+        Self::new(self.negative != rhs.negative, digits)
+    }
+
+    // Quotient rounded toward zero and a remainder with the sign of `self`; `None` for a zero divisor.
+    pub fn div_rem(&self, rhs: &Self) -> Option<(Self, Self)> {
+        if rhs.is_zero() {
+            return None;
+        }
+        if rhs.digits.len() == 1 {
+            let mut quotient = self.abs();
+            let remainder = quotient.div_small(rhs.digits[0]);
+            return Some((Self::new(self.negative != rhs.negative, quotient.digits), Self::new(self.negative, vec![remainder])));
+        }
+
+        // Long division one bit at a time, which is plenty for the numbers scripts work with.
+        let divisor = rhs.abs();
+        let mut quotient = vec![0u32; self.digits.len()];
+        let mut remainder = Self::zero();
+        for index in (0..self.bits()).rev() {
+            remainder = remainder.shl(1);
+            if self.bit(index) {
+                remainder = remainder.add_small(1);
+            }
+            if Self::cmp_magnitude(&remainder.digits, &divisor.digits) != Ordering::Less {
+                remainder = Self::new(false, Self::sub_magnitude(&remainder.digits, &divisor.digits));
+                quotient[(index / 32) as usize] |= 1 << (index % 32);
+            }
+        }
+        Some((Self::new(self.negative != rhs.negative, quotient), Self::new(self.negative, remainder.digits)))
+    }
+
+    // Quotient rounded toward negative infinity and a remainder with the sign of `rhs`.
+    pub fn div_mod_floor(&self, rhs: &Self) -> Option<(Self, Self)> {
+        let (quotient, remainder) = self.div_rem(rhs)?;
+        match !remainder.is_zero() && remainder.negative != rhs.negative {
+            true => Some((quotient.sub(&Self::from(1)), remainder.add(rhs))),
+            false => Some((quotient, remainder)),
+        }
+    }
+
+    pub fn pow(&self, mut exponent: u32) -> Self {
+        let mut result = Self::from(1);
+        let mut base = self.clone();
+        while exponent > 0 {
+            if exponent & 1 == 1 {
+                result = result.mul(&base);
+            }
+            exponent >>= 1;
+            if exponent > 0 {
+                base = base.mul(&base);
+            }
+        }
+        result
+    }
+
+    // Roughly how many bits `self ** exponent` takes, to refuse powers too large to compute.
+    pub fn pow_bits(&self, exponent: u32) -> u64 {
+        self.bits().saturating_mul(exponent as u64)
+    }
+
+    pub fn gcd(&self, rhs: &Self) -> Self {
+        let (mut a, mut b) = (self.abs(), rhs.abs());
+        while !b.is_zero() {
+            let (_, remainder) = a.div_rem(&b).expect("Checked above.");
+            (a, b) = (b, remainder);
+        }
+        a
+    }
+}
+
+impl From<i64> for BigInt {
+    fn from(value: i64) -> Self {
+        let magnitude = value.unsigned_abs();
+        Self::new(value < 0, vec![magnitude as u32, (magnitude >> 32) as u32])
+    }
+}
+
+impl PartialOrd for BigInt {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for BigInt {
+    fn cmp(&self, other: &Self) -> Ordering {
+        match (self.negative, other.negative) {
+            (false, true) => Ordering::Greater,
+            (true, false) => Ordering::Less,
+            (false, false) => Self::cmp_magnitude(&self.digits, &other.digits),
+            (true, true) => Self::cmp_magnitude(&other.digits, &self.digits),
+        }
+    }
+}
+
+impl fmt::Display for BigInt {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut chunks = Vec::new();
+        let mut rest = self.abs();
+        while !rest.is_zero() {
+            chunks.push(rest.div_small(DECIMAL_CHUNK));
+        }
+
+        if self.negative {
+            write!(f, "-")?;
+        }
+        match chunks.pop() {
+            Some(first) => write!(f, "{first}")?,
+            None => write!(f, "0")?,
+        }
+        for chunk in chunks.iter().rev() {
+            write!(f, "{chunk:09}")?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const VALUES: &[i128] = &[0, 1, -1, 7, -7, 1 << 31, -(1 << 32), (1 << 62) + 5, -(1 << 62) - 3, i64::MAX as i128, i64::MIN as i128];
+
+    fn big(value: i128) -> BigInt {
+        BigInt::parse(&value.to_string()).unwrap()
+    }
+
+    #[test]
+    fn arithmetic_with_every_sign() {
+        for &a in VALUES {
+            for &b in VALUES {
+                assert_eq!(big(a).add(&big(b)), big(a + b), "{a} + {b}");
+                assert_eq!(big(a).sub(&big(b)), big(a - b), "{a} - {b}");
+                assert_eq!(big(a).mul(&big(b)), big(a * b), "{a} * {b}");
+            }
+        }
+    }
+
+    #[test]
+    fn division_with_every_sign() {
+        for &a in VALUES {
+            for &b in VALUES.iter().filter(|b| **b != 0) {
+                assert_eq!(big(a).div_rem(&big(b)), Some((big(a / b), big(a % b))), "{a} / {b}");
+                let (mut quotient, mut remainder) = (a / b, a % b);
+                if remainder != 0 && (remainder < 0) != (b < 0) {
+                    quotient -= 1;
+                    remainder += b;
+                }
+                assert_eq!(big(a).div_mod_floor(&big(b)), Some((big(quotient), big(remainder))), "{a} ~/ {b}");
+            }
+            assert_eq!(big(a).div_rem(&BigInt::zero()), None);
+            assert_eq!(big(a).div_mod_floor(&BigInt::zero()), None);
+        }
+    }
+
+    #[test]
+    fn bitwise_with_every_sign() {
+        for &a in VALUES {
+            for &b in VALUES {
+                assert_eq!(big(a).bitwise(&big(b), |a, b| a & b), big(a & b), "{a} & {b}");
+                assert_eq!(big(a).bitwise(&big(b), |a, b| a | b), big(a | b), "{a} | {b}");
+                assert_eq!(big(a).bitwise(&big(b), |a, b| a ^ b), big(a ^ b), "{a} ^ {b}");
+            }
+            for shift in [0, 1, 31, 32, 33, 63, 200] {
+                assert_eq!(big(a).shr(shift), big(a >> shift.min(127)), "{a} >> {shift}");
+            }
+        }
+    }
+
+    #[test]
+    fn parse_and_display_round_trip() {
+        for text in ["0", "1", "-1", "999999999", "1000000000", "-18446744073709551616", "123456789012345678901234567890123456789"] {
+            assert_eq!(BigInt::parse(text).unwrap().to_string(), text);
+        }
+        assert_eq!(BigInt::parse("-0").unwrap(), BigInt::zero());
+        assert_eq!(BigInt::parse("000123").unwrap().to_string(), "123");
+        for text in ["", "-", "+1", "12a", "1.5", " 1"] {
+            assert_eq!(BigInt::parse(text), None, "{text:?}");
+        }
+    }
+
+    #[test]
+    fn parse_radix() {
+        assert_eq!(BigInt::parse_radix("ff", 16), Some(big(255)));
+        assert_eq!(BigInt::parse_radix("zz", 36), Some(big(35 * 36 + 35)));
+        assert_eq!(BigInt::parse_radix("1010", 2), Some(big(10)));
+        let digits = format!("{:x}", u128::MAX);
+        assert_eq!(BigInt::parse_radix(&digits, 16).unwrap().to_string(), u128::MAX.to_string());
+        for (digits, radix) in [("", 16), ("g", 16), ("2", 2), ("-1", 10)] {
+            assert_eq!(BigInt::parse_radix(digits, radix), None, "{digits:?} in radix {radix}");
+        }
+    }
+
+    #[test]
+    fn to_f64_rounds_to_nearest() {
+        let two_53 = 1i128 << 53;
+        assert_eq!(big(two_53).to_f64(), 9007199254740992.0);
+        assert_eq!(big(two_53 + 1).to_f64(), 9007199254740992.0);
+        assert_eq!(big(two_53 + 3).to_f64(), 9007199254740996.0);
+        assert_eq!(big(-two_53 - 3).to_f64(), -9007199254740996.0);
+
+        // A tie, and the same just past it, where a truncated quotient would still look like a tie.
+        let two_64 = 1i128 << 64;
+        assert_eq!(big(two_64 + (1 << 11)).to_f64(), 18446744073709551616.0);
+        assert_eq!(big(two_64 + (1 << 11) + 1).to_f64(), 18446744073709555712.0);
+
+        // Rounding the top digits first would land on the tie below and then round down to even.
+        assert_eq!(big((two_53 + 1) << 32 | 1).to_f64(), 3.868562622766814e25);
+    }
+
+    #[test]
+    fn shr_rounds_toward_negative_infinity() {
+        for value in [0, 1, 7, -1, -7, -8, (1 << 100) + 5, -(1 << 100) - 5, -(1 << 100)] {
+            for bits in [0, 1, 3, 31, 32, 33, 64, 100, 101, 200] {
+                assert_eq!(big(value).shr(bits), big(value >> bits.min(127)), "{value} >> {bits}");
+            }
+        }
+    }
+
+    #[test]
+    fn to_f64_at_the_largest_float() {
+        let max = BigInt::from((1 << 53) - 1).shl(971);
+        let half_unit = BigInt::from(1).shl(970);
+        assert_eq!(max.to_f64(), f64::MAX);
+        assert_eq!(max.add(&half_unit).sub(&BigInt::from(1)).to_f64(), f64::MAX);
+        assert_eq!(max.add(&half_unit).to_f64(), f64::INFINITY);
+        assert_eq!(BigInt::from(1).shl(1024).to_f64(), f64::INFINITY);
+        assert_eq!(BigInt::from(-1).shl(5000).to_f64(), f64::NEG_INFINITY);
+    }
+}
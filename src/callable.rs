@@ -5,6 +5,10 @@ use crate::{interpreter::Interpreter, object::Object, error::LoxError};
 pub trait LoxCallable: Display {
     fn arity(&self) -> usize;
     fn name(&self) -> &str;
+    // Where the function was declared, `None` for natives.
+    fn line(&self) -> Option<usize> {
+        None
+    }
     fn call(&self, interpreter: &mut Interpreter, arguments: &[Object]) -> Result<Object, LoxError>; 
 }
 
@@ -32,3 +36,31 @@ impl Display for NativeClock {
         write!(f, "<native fn>")
     }
 }
+
+// A native function backed by a plain Rust function, for natives that need no state of their own.
+#[derive(Clone, Copy)]
+pub struct NativeFunction {
+    pub name: &'static str,
+    pub arity: usize,
+    pub function: fn(&mut Interpreter, &[Object]) -> Result<Object, LoxError>,
+}
+
+impl LoxCallable for NativeFunction {
+    fn arity(&self) -> usize {
+        self.arity
+    }
+
+    fn name(&self) -> &str {
+        self.name
+    }
+
+    fn call(&self, interpreter: &mut Interpreter, arguments: &[Object]) -> Result<Object, LoxError> {
+        (self.function)(interpreter, arguments)
+    }
+}
+
+impl Display for NativeFunction {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "<native fn>")
+    }
+}
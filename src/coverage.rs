@@ -0,0 +1,278 @@
+use std::{cell::RefCell, collections::{BTreeMap, HashMap}, fs, io::{self, Write}, path::PathBuf, rc::Rc};
+
+use crate::{expr::*, stmt::*, object::Object, token::Token, error::LoxError, environment::Environment,
+    callable::LoxCallable, hook::{ExecutionHook, Branch}};
+
+// Records which statements, branches and functions ran. Everything that could run is
+// listed up front from the AST, so what never ran shows up in the reports too.
+pub struct Coverage {
+    path: PathBuf,
+    source: Vec<String>,
+    output: PathBuf,
+    statements: HashMap<Stmt, usize>,   // line of each statement; blocks are left out
+    hits: HashMap<Stmt, usize>,
+    branches: Vec<(Branch, usize)>,     // in source order, with their line
+    branch_index: HashMap<Branch, usize>,
+    branch_hits: Vec<[usize; 2]>,       // taken, not taken
+    functions: Vec<(String, usize)>,
+    calls: HashMap<(String, usize), usize>,
+}
+
+impl ExecutionHook for Coverage {
+    fn statement(&mut self, stmt: &Stmt, _environment: &Rc<RefCell<Environment>>) -> Result<(), LoxError> {
+        if self.statements.contains_key(stmt) {
+            *self.hits.entry(stmt.clone()).or_default() += 1;
+        }
+        Ok(())
+    }
+
+    fn enter(&mut self, function: &dyn LoxCallable, _paren: &Token, _arguments: &[Object]) {
+        if let Some(line) = function.line() {
+            *self.calls.entry((function.name().to_owned(), line)).or_default() += 1;
+        }
+    }
+
+    fn branch(&mut self, site: &Branch, taken: bool) {
+        if let Some(&index) = self.branch_index.get(site) {
+            self.branch_hits[index][usize::from(!taken)] += 1;
+        }
+    }
+
+    fn finish(&mut self) -> io::Result<()> {
+        let mut lcov = io::BufWriter::new(fs::File::create(&self.output)?);
+        self.lcov(&mut lcov)?;
+        lcov.flush()?;
+        self.annotate(&mut io::stderr().lock())
+    }
+}
+
+impl Coverage {
+    // `path` names the script in the lcov output, which is written to `output`;
+    // the annotated source goes to stderr.
+    pub fn new(path: PathBuf, source: &str, output: PathBuf, statements: &[Stmt], lines: HashMap<Stmt, usize>) -> Self {
+        let mut inventory = Inventory { lines, line: 0, statements: HashMap::new(), branches: Vec::new(), functions: Vec::new() };
+        for stmt in statements {
+            inventory.statement(stmt).expect("Listing the AST cannot fail.");
+        }
+
+        let branch_index = inventory.branches.iter().enumerate().map(|(i, (site, _))| (site.clone(), i)).collect();
+        Self {
+            path,
+            source: source.lines().map(str::to_owned).collect(),
+            output,
+            statements: inventory.statements,
+            hits: HashMap::new(),
+            branch_hits: vec![[0; 2]; inventory.branches.len()],
+            branches: inventory.branches,
+            branch_index,
+            functions: inventory.functions,
+            calls: HashMap::new(),
+        }
+    }
+
+    // How often each line with statements ran: the most any statement starting there did.
+    fn line_hits(&self) -> BTreeMap<usize, usize> {
+        let mut lines = BTreeMap::new();
+        for (stmt, line) in &self.statements {
+            let hits = self.hits.get(stmt).copied().unwrap_or(0);
+            let entry = lines.entry(*line).or_insert(0);
+            *entry = hits.max(*entry);
+        }
+        lines
+    }
+
+    fn lcov(&self, out: &mut impl Write) -> io::Result<()> {
+        writeln!(out, "TN:")?;
+        writeln!(out, "SF:{}", self.path.display())?;
+
+        for (name, line) in &self.functions {
+            writeln!(out, "FN:{line},{name}")?;
+        }
+        let mut functions_hit = 0;
+        for (name, line) in &self.functions {
+            let calls = self.calls.get(&(name.clone(), *line)).copied().unwrap_or(0);
+            functions_hit += usize::from(calls > 0);
+            writeln!(out, "FNDA:{calls},{name}")?;
+        }
+        writeln!(out, "FNF:{}", self.functions.len())?;
+        writeln!(out, "FNH:{functions_hit}")?;
+
+        let mut branches_hit = 0;
+        let mut block = (0, 0);     // line, index of the site on that line
+        for ((_, line), hits) in self.branches.iter().zip(&self.branch_hits) {
+            block = if block.0 == *line { (*line, block.1 + 1) } else { (*line, 0) };
+            let reached = hits[0] + hits[1] > 0;
+            for (branch, count) in hits.iter().enumerate() {
+                branches_hit += usize::from(*count > 0);
+                match reached {
+                    true => writeln!(out, "BRDA:{line},{},{branch},{count}", block.1)?,
+                    false => writeln!(out, "BRDA:{line},{},{branch},-", block.1)?,
+                }
+            }
+        }
+        writeln!(out, "BRF:{}", self.branches.len() * 2)?;
+        writeln!(out, "BRH:{branches_hit}")?;
+
+        let lines = self.line_hits();
+        for (line, hits) in &lines {
+            writeln!(out, "DA:{line},{hits}")?;
+        }
+        writeln!(out, "LF:{}", lines.len())?;
+        writeln!(out, "LH:{}", lines.values().filter(|hits| **hits > 0).count())?;
+        writeln!(out, "end_of_record")
+    }
+
+    // The source with how often each line ran, `#####` for lines that never did,
+    // and `[taken/not taken]` for each branch on a line.
+    fn annotate(&self, out: &mut impl Write) -> io::Result<()> {
+        let lines = self.line_hits();
+        let mut branches: BTreeMap<usize, Vec<String>> = BTreeMap::new();
+        for ((_, line), hits) in self.branches.iter().zip(&self.branch_hits) {
+            branches.entry(*line).or_default().push(format!("[{}/{}]", hits[0], hits[1]));
+        }
+
+        for (i, text) in self.source.iter().enumerate() {
+            let line = i + 1;
+            let count = match lines.get(&line) {
+                Some(0) => "#####".to_owned(),
+                Some(hits) => hits.to_string(),
+                None => String::new(),
+            };
+            match branches.get(&line) {
+                Some(sites) => writeln!(out, "{count:>9} | {text}    {}", sites.join(" "))?,
+                None => writeln!(out, "{count:>9} | {text}")?,
+            }
+        }
+
+        let covered = |hit: usize, total: usize| match total {
+            0 => format!("{hit}/{total}"),
+            _ => format!("{hit}/{total} ({:.1}%)", hit as f64 * 100.0 / total as f64),
+        };
+        let branch_hits: usize = self.branch_hits.iter().flatten().filter(|count| **count > 0).count();
+        let functions_hit = self.functions.iter().filter(|function| self.calls.contains_key(function)).count();
+        writeln!(out)?;
+        writeln!(out, "Lines: {}", covered(lines.values().filter(|hits| **hits > 0).count(), lines.len()))?;
+        writeln!(out, "Branches: {}", covered(branch_hits, self.branches.len() * 2))?;
+        writeln!(out, "Functions: {}", covered(functions_hit, self.functions.len()))
+    }
+}
+
+// Walks the AST once to list every statement, branch and function.
+struct Inventory {
+    lines: HashMap<Stmt, usize>,
+    line: usize,    // line of the statement being walked, for nodes without one of their own
+    statements: HashMap<Stmt, usize>,
+    branches: Vec<(Branch, usize)>,
+    functions: Vec<(String, usize)>,
+}
+
+impl Inventory {
+    fn statement(&mut self, stmt: &Stmt) -> Result<(), LoxError> {
+        if let Some(&line) = self.lines.get(stmt) {
+            self.line = line;
+            if !matches!(stmt, Stmt::Block(_)) {
+                self.statements.insert(stmt.clone(), line);
+            }
+        }
+        stmt.accept(self)
+    }
+}
+
+impl ExprVisitor for Inventory {
+    type Output = ();
+
+    fn visit_assign_expr(&mut self, expr: Rc<AssignExpr>) -> Result<Self::Output, LoxError> {
+        expr.value.accept(self)
+    }
+
+    fn visit_binary_expr(&mut self, expr: Rc<BinaryExpr>) -> Result<Self::Output, LoxError> {
+        expr.left.accept(self)?;
+        expr.right.accept(self)
+    }
+
+    fn visit_call_expr(&mut self, expr: Rc<CallExpr>) -> Result<Self::Output, LoxError> {
+        expr.callee.accept(self)?;
+        expr.arguments.iter().try_for_each(|argument| argument.accept(self))
+    }
+
+    fn visit_grouping_expr(&mut self, expr: Rc<GroupingExpr>) -> Result<Self::Output, LoxError> {
+        expr.expression.accept(self)
+    }
+
+    fn visit_interpolation_expr(&mut self, expr: Rc<InterpolationExpr>) -> Result<Self::Output, LoxError> {
+        expr.parts.iter().try_for_each(|part| part.accept(self))
+    }
+
+    fn visit_literal_expr(&mut self, _expr: Rc<LiteralExpr>) -> Result<Self::Output, LoxError> {
+        Ok(())
+    }
+
+    fn visit_logical_expr(&mut self, expr: Rc<LogicalExpr>) -> Result<Self::Output, LoxError> {
+        expr.left.accept(self)?;
+        self.branches.push((Branch::Expr(Expr::Logical(Rc::clone(&expr))), expr.operator.line));
+        expr.right.accept(self)
+    }
+
+    fn visit_unary_expr(&mut self, expr: Rc<UnaryExpr>) -> Result<Self::Output, LoxError> {
+        expr.right.accept(self)
+    }
+
+    fn visit_variable_expr(&mut self, _expr: Rc<VariableExpr>) -> Result<Self::Output, LoxError> {
+        Ok(())
+    }
+}
+
+impl StmtVisitor for Inventory {
+    type Output = ();
+
+    fn visit_break_stmt(&mut self, _stmt: Rc<BreakStmt>) -> Result<Self::Output, LoxError> {
+        Ok(())
+    }
+
+    fn visit_block_stmt(&mut self, stmt: Rc<BlockStmt>) -> Result<Self::Output, LoxError> {
+        stmt.statements.iter().try_for_each(|stmt| self.statement(stmt))
+    }
+
+    fn visit_expression_stmt(&mut self, stmt: Rc<ExpressionStmt>) -> Result<Self::Output, LoxError> {
+        stmt.expression.accept(self)
+    }
+
+    fn visit_function_stmt(&mut self, stmt: Rc<FunctionStmt>) -> Result<Self::Output, LoxError> {
+        self.functions.push((stmt.name.lexeme.clone(), stmt.name.line));
+        stmt.body.iter().try_for_each(|stmt| self.statement(stmt))
+    }
+
+    fn visit_if_stmt(&mut self, stmt: Rc<IfStmt>) -> Result<Self::Output, LoxError> {
+        let line = self.line;
+        stmt.condition.accept(self)?;
+        self.branches.push((Branch::Stmt(Stmt::If(Rc::clone(&stmt))), line));
+        self.statement(&stmt.then_branch)?;
+        match &stmt.else_branch {
+            Some(else_branch) => self.statement(else_branch),
+            None => Ok(()),
+        }
+    }
+
+    fn visit_print_stmt(&mut self, stmt: Rc<PrintStmt>) -> Result<Self::Output, LoxError> {
+        stmt.expression.accept(self)
+    }
+
+    fn visit_return_stmt(&mut self, stmt: Rc<ReturnStmt>) -> Result<Self::Output, LoxError> {
+        stmt.value.accept(self)
+    }
+
+    fn visit_var_stmt(&mut self, stmt: Rc<VarStmt>) -> Result<Self::Output, LoxError> {
+        match &stmt.initializer {
+            Some(initializer) => initializer.accept(self),
+            None => Ok(()),
+        }
+    }
+
+    // The `while` a `for` loop turns into has no line of its own and takes the loop's.
+    fn visit_while_stmt(&mut self, stmt: Rc<WhileStmt>) -> Result<Self::Output, LoxError> {
+        let line = self.line;
+        stmt.condition.accept(self)?;
+        self.branches.push((Branch::Stmt(Stmt::While(Rc::clone(&stmt))), line));
+        self.statement(&stmt.body)
+    }
+}
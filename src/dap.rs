@@ -0,0 +1,406 @@
+use std::{cell::RefCell, collections::BTreeSet, fs, io, path::{Path, PathBuf}, rc::Rc, sync::mpsc, thread};
+
+use crate::{interpreter::Interpreter, environment::Environment, error::LoxError, json::Json, lsp::{read_message, write_message},
+    debugger::{Debugger, DebugState, Frontend, Pause, Resume, describe, scopes}, hook::Stream, files::Permissions};
+
+// Lox programs run on a single thread; DAP still wants an id for it.
+const THREAD_ID: usize = 1;
+
+// The adapter side of the Debug Adapter Protocol. Requests are read on their own thread
+// so that `pause` and `setBreakpoints` can reach a program that is running.
+struct Connection {
+    seq: usize,
+    incoming: mpsc::Receiver<String>,
+    closed: bool,
+}
+
+impl Connection {
+    fn new() -> Self {
+        let (sender, incoming) = mpsc::channel();
+        thread::spawn(move || {
+            let mut reader = io::stdin().lock();
+            while let Ok(Some(body)) = read_message(&mut reader) {
+                if sender.send(body).is_err() {
+                    break;
+                }
+            }
+        });
+        Self { seq: 0, incoming, closed: false }
+    }
+
+    // Waits for the next request; `None` once the client has gone.
+    fn receive(&mut self) -> Option<Json> {
+        while !self.closed {
+            match self.incoming.recv() {
+                Ok(body) => if let Ok(message) = Json::parse(&body) {
+                    return Some(message);
+                },
+                Err(_) => self.closed = true,
+            }
+        }
+        None
+    }
+
+    fn try_receive(&mut self) -> Option<Json> {
+        loop {
+            match self.incoming.try_recv() {
+                Ok(body) => if let Ok(message) = Json::parse(&body) {
+                    return Some(message);
+                },
+                Err(mpsc::TryRecvError::Empty) => return None,
+                Err(mpsc::TryRecvError::Disconnected) => {
+                    self.closed = true;
+                    return None;
+                },
+            }
+        }
+    }
+
+    fn respond(&mut self, request: &Json, result: Result<Json, String>) {
+        let (success, body) = match result {
+            Ok(body) => (true, body),
+            Err(message) => (false, Json::object([("error", Json::object([("id", Json::Num(1.0)), ("format", message.into())]))])),
+        };
+        self.send(vec![
+            ("type", "response".into()),
+            ("request_seq", request.get("seq").clone()),
+            ("success", success.into()),
+            ("command", request.get("command").clone()),
+            ("body", body),
+        ]);
+    }
+
+    fn event(&mut self, event: &str, body: Json) {
+        self.send(vec![("type", "event".into()), ("event", event.into()), ("body", body)]);
+    }
+
+    fn send(&mut self, fields: Vec<(&str, Json)>) {
+        self.seq += 1;
+        let mut message = vec![("seq".to_owned(), self.seq.into())];
+        message.extend(fields.into_iter().map(|(key, value)| (key.to_owned(), value)));
+        // Nothing useful can be done if the client stopped listening.
+        let _ = write_message(&mut io::stdout().lock(), &Json::Object(message));
+    }
+}
+
+// What `launch` and the requests before `configurationDone` asked for.
+struct Launch {
+    program: PathBuf,
+    stop_on_entry: bool,
+    breakpoints: BTreeSet<usize>,
+    permissions: Permissions,
+}
+
+// Runs a whole debug session over stdin and stdout. Scripts get the permissions `rlox` was given
+// along with those `launch` asks for.
+pub fn serve(permissions: Permissions) -> io::Result<()> {
+    let connection = Rc::new(RefCell::new(Connection::new()));
+    let Some(launch) = configure(&connection, permissions) else {
+        return Ok(());
+    };
+
+    let source = match fs::read_to_string(&launch.program) {
+        Ok(source) => source,
+        Err(e) => {
+            let mut connection = connection.borrow_mut();
+            let message = format!("Cannot read '{}': {e}.\n", launch.program.display());
+            connection.event("output", Json::object([("category", "stderr".into()), ("output", message.into())]));
+            connection.event("terminated", Json::object([]));
+            return Ok(());
+        },
+    };
+
+    let (result, diagnostics) = LoxError::capture(|| -> Result<(), LoxError> {
+        let mut interp = Interpreter::new();
+        interp.set_input(Box::new(io::empty()));     // standard input carries the protocol
+        interp.permissions = launch.permissions;
+        let (parser, statements) = crate::compile(source, &mut interp)?;
+
+        let frontend = Adapter { connection: Rc::clone(&connection), program: launch.program.clone(), references: Vec::new() };
+        let debugger = Debugger::new(parser.statement_lines(), &interp.globals, Box::new(frontend))
+            .stop_on_entry(launch.stop_on_entry)
+            .breakpoints(launch.breakpoints);
+        interp.attach(Box::new(debugger));
+        interp.interpret(statements)
+    });
+
+    let mut connection = connection.borrow_mut();
+    for diagnostic in diagnostics {
+        connection.event("output", Json::object([
+            ("category", "stderr".into()),
+            ("output", format!("[line {}] Error: {}\n", diagnostic.line, diagnostic.message).into()),
+        ]));
+    }
+    let exit_code = match result {
+        Ok(()) | Err(LoxError::Abort) => 0,
+        Err(LoxError::RuntimeError) => 70,
+        Err(_) => 65,
+    };
+    connection.event("exited", Json::object([("exitCode", exit_code.into())]));
+    connection.event("terminated", Json::object([]));
+
+    while let Some(request) = connection.receive() {
+        match request.get("command").as_str() {
+            Some("disconnect") => {
+                connection.respond(&request, Ok(Json::Null));
+                break;
+            },
+            _ => connection.respond(&request, Err("The program has finished.".to_owned())),
+        }
+    }
+    Ok(())
+}
+
+// Answers requests until the client is done configuring and the program can start. Clients may
+// send `configurationDone` before or after `launch`; the program starts once both have arrived.
+fn configure(connection: &Rc<RefCell<Connection>>, permissions: Permissions) -> Option<Launch> {
+    let mut connection = connection.borrow_mut();
+    let mut launch = None;
+    let mut breakpoints = Vec::new();
+    let mut configured = false;
+
+    while let Some(request) = connection.receive() {
+        let arguments = request.get("arguments");
+        match request.get("command").as_str().unwrap_or_default() {
+            "initialize" => {
+                connection.respond(&request, Ok(Json::object([
+                    ("supportsConfigurationDoneRequest", true.into()),
+                    ("supportsTerminateRequest", true.into()),
+                ])));
+                connection.event("initialized", Json::object([]));
+            },
+            "launch" => match (arguments.get("program").as_str(), granted(&permissions, arguments)) {
+                (Some(program), Ok(permissions)) => {
+                    let program = fs::canonicalize(program).unwrap_or_else(|_| PathBuf::from(program));
+                    let stop_on_entry = arguments.get("stopOnEntry").as_bool().unwrap_or(false);
+                    launch = Some(Launch { program, stop_on_entry, breakpoints: BTreeSet::new(), permissions });
+                    connection.respond(&request, Ok(Json::Null));
+                },
+                (None, _) => connection.respond(&request, Err("Missing 'program' to launch.".to_owned())),
+                (_, Err(message)) => connection.respond(&request, Err(message)),
+            },
+            // Breakpoints may arrive before `launch` says which file is debugged.
+            "setBreakpoints" => {
+                let lines = requested_lines(arguments);
+                connection.respond(&request, Ok(Json::object([("breakpoints", verified(&lines))])));
+                breakpoints.push((source_path(arguments), lines));
+            },
+            "threads" => connection.respond(&request, Ok(threads())),
+            "configurationDone" => {
+                connection.respond(&request, Ok(Json::Null));
+                configured = true;
+            },
+            "disconnect" | "terminate" => {
+                connection.respond(&request, Ok(Json::Null));
+                return None;
+            },
+            command => connection.respond(&request, Err(format!("Unsupported request '{command}'."))),
+        }
+        if let Some(mut launch) = launch.take_if(|_| configured) {
+            for (path, lines) in breakpoints {
+                if path.as_deref() == Some(launch.program.as_path()) {
+                    launch.breakpoints = lines.into_iter().collect();
+                }
+            }
+            return Some(launch);
+        }
+    }
+    None
+}
+
+// The debugger front end that forwards pauses to the client and waits for its requests.
+struct Adapter {
+    connection: Rc<RefCell<Connection>>,
+    program: PathBuf,
+    references: Vec<Rc<RefCell<Environment>>>,     // scopes handed out while paused; a reference is its index + 1
+}
+
+impl Frontend for Adapter {
+    fn paused(&mut self, state: &mut DebugState, reason: Pause) -> Resume {
+        let connection = Rc::clone(&self.connection);
+        let mut connection = connection.borrow_mut();
+        if connection.closed {
+            return Resume::Stop;
+        }
+
+        let reason = match reason {
+            Pause::Entry => "entry",
+            Pause::Step => "step",
+            Pause::Breakpoint => "breakpoint",
+            Pause::Request => "pause",
+        };
+        connection.event("stopped", Json::object([
+            ("reason", reason.into()),
+            ("threadId", THREAD_ID.into()),
+            ("allThreadsStopped", true.into()),
+        ]));
+
+        self.references.clear();
+        while let Some(request) = connection.receive() {
+            let arguments = request.get("arguments");
+            let resume = match request.get("command").as_str().unwrap_or_default() {
+                "continue" => Some(Resume::Continue),
+                "next" => Some(Resume::StepOver),
+                "stepIn" => Some(Resume::StepIn),
+                "stepOut" => Some(Resume::StepOut),
+                "disconnect" | "terminate" => Some(Resume::Stop),
+                _ => None,
+            };
+            if let Some(resume) = resume {
+                let body = match resume {
+                    Resume::Continue => Json::object([("allThreadsContinued", true.into())]),
+                    _ => Json::Null,
+                };
+                connection.respond(&request, Ok(body));
+                return resume;
+            }
+
+            let result = self.request(state, request.get("command").as_str().unwrap_or_default(), arguments);
+            connection.respond(&request, result);
+        }
+        Resume::Stop
+    }
+
+    fn poll(&mut self, state: &mut DebugState) -> bool {
+        let connection = Rc::clone(&self.connection);
+        let mut connection = connection.borrow_mut();
+        let mut pause = connection.closed;
+        while let Some(request) = connection.try_receive() {
+            match request.get("command").as_str().unwrap_or_default() {
+                "pause" => {
+                    connection.respond(&request, Ok(Json::Null));
+                    pause = true;
+                },
+                // Pausing with the connection closed stops the program.
+                "disconnect" | "terminate" => {
+                    connection.respond(&request, Ok(Json::Null));
+                    connection.closed = true;
+                    pause = true;
+                },
+                command => {
+                    let result = self.request(state, command, request.get("arguments"));
+                    connection.respond(&request, result);
+                },
+            }
+        }
+        pause
+    }
+
+    fn output(&mut self, text: &str, stream: Stream) -> bool {
+        let category = match stream {
+            Stream::Stdout => "stdout",
+            Stream::Stderr => "stderr",
+        };
+        self.connection.borrow_mut().event("output", Json::object([
+            ("category", category.into()),
+            ("output", text.into()),
+        ]));
+        true
+    }
+}
+
+impl Adapter {
+    // Requests that can be answered whether the program is paused or running.
+    fn request(&mut self, state: &mut DebugState, command: &str, arguments: &Json) -> Result<Json, String> {
+        match command {
+            "threads" => Ok(threads()),
+            "setBreakpoints" => {
+                let lines = requested_lines(arguments);
+                if source_path(arguments).as_deref() == Some(self.program.as_path()) {
+                    state.breakpoints = lines.iter().copied().collect();
+                    Ok(Json::object([("breakpoints", verified(&lines))]))
+                } else {
+                    let unverified = lines.iter().map(|line| Json::object([("verified", false.into()), ("line", (*line).into())])).collect();
+                    Ok(Json::object([("breakpoints", Json::Array(unverified))]))
+                }
+            },
+            "stackTrace" => {
+                let source = Json::object([
+                    ("name", self.program.file_name().map(|name| name.to_string_lossy().into_owned()).into()),
+                    ("path", self.program.to_string_lossy().into_owned().into()),
+                ]);
+                let frames: Vec<Json> = state.frames.iter().rev().enumerate().map(|(id, frame)| Json::object([
+                    ("id", id.into()),
+                    ("name", frame.name.as_str().into()),
+                    ("source", source.clone()),
+                    ("line", frame.line.into()),
+                    ("column", Json::Num(1.0)),
+                ])).collect();
+                Ok(Json::object([("totalFrames", frames.len().into()), ("stackFrames", Json::Array(frames))]))
+            },
+            "scopes" => {
+                let frame = Self::frame(state, arguments.get("frameId"))?;
+                let scopes = scopes(&frame.environment).into_iter().map(|(name, environment)| {
+                    self.references.push(environment);
+                    Json::object([
+                        ("name", name.into()),
+                        ("variablesReference", self.references.len().into()),
+                        ("expensive", false.into()),
+                    ])
+                }).collect();
+                Ok(Json::object([("scopes", Json::Array(scopes))]))
+            },
+            "variables" => {
+                let environment = arguments.get("variablesReference").as_usize()
+                    .and_then(|reference| self.references.get(reference.wrapping_sub(1)))
+                    .ok_or("Unknown variables reference.")?;
+                let variables = environment.borrow().bindings().into_iter().map(|(name, value)| Json::object([
+                    ("name", name.into()),
+                    ("value", describe(&value).into()),
+                    ("variablesReference", Json::Num(0.0)),
+                ])).collect();
+                Ok(Json::object([("variables", Json::Array(variables))]))
+            },
+            // Only variable names can be evaluated: anything else would need to run code.
+            "evaluate" => {
+                let expression = arguments.get("expression").as_str().unwrap_or_default().trim();
+                let frame = match arguments.get("frameId") {
+                    Json::Null => state.frames.last().ok_or("No frame.")?,
+                    id => Self::frame(state, id)?,
+                };
+                match frame.environment.borrow().lookup(expression) {
+                    Some(value) => Ok(Json::object([("result", describe(&value).into()), ("variablesReference", Json::Num(0.0))])),
+                    None => Err(format!("Undefined variable '{expression}'.")),
+                }
+            },
+            _ => Err(format!("Unsupported request '{command}'.")),
+        }
+    }
+
+    fn frame<'a>(state: &'a DebugState, id: &Json) -> Result<&'a crate::debugger::Frame, String> {
+        id.as_usize().and_then(|id| state.frames.iter().rev().nth(id)).ok_or_else(|| "Unknown frame.".to_owned())
+    }
+}
+
+fn threads() -> Json {
+    Json::object([("threads", Json::Array(vec![Json::object([("id", THREAD_ID.into()), ("name", "main".into())])]))])
+}
+
+fn requested_lines(arguments: &Json) -> Vec<usize> {
+    arguments.get("breakpoints").as_array().iter().filter_map(|breakpoint| breakpoint.get("line").as_usize()).collect()
+}
+
+fn verified(lines: &[usize]) -> Json {
+    Json::Array(lines.iter().map(|line| Json::object([("verified", true.into()), ("line", (*line).into())])).collect())
+}
+
+// The permissions `rlox` was given, with the directories in the `allowRead` and `allowWrite`
+// arrays of `launch` added.
+fn granted(permissions: &Permissions, arguments: &Json) -> Result<Permissions, String> {
+    let mut permissions = permissions.clone();
+    for (key, write) in [("allowRead", false), ("allowWrite", true)] {
+        for dir in arguments.get(key).as_array() {
+            let dir = dir.as_str().ok_or_else(|| format!("'{key}' need an array of directories."))?;
+            match write {
+                false => permissions.allow_read(dir),
+                true => permissions.allow_write(dir),
+            }.map_err(|e| format!("Cannot allow access to '{dir}': {e}."))?;
+        }
+    }
+    Ok(permissions)
+}
+
+fn source_path(arguments: &Json) -> Option<PathBuf> {
+    let path = Path::new(arguments.get("source").get("path").as_str()?);
+    Some(fs::canonicalize(path).unwrap_or_else(|_| path.to_owned()))
+}
@@ -0,0 +1,305 @@
+use std::{cell::RefCell, collections::{BTreeSet, HashMap}, io::{self, Write}, rc::Rc};
+
+use crate::{stmt::Stmt, object::Object, token::Token, error::LoxError, environment::Environment,
+    callable::LoxCallable, hook::{ExecutionHook, Stream}};
+
+// How the program goes on after a pause.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Resume {
+    Continue,
+    StepIn,     // stop at the very next line
+    StepOver,   // stop at the next line of this call or a caller
+    StepOut,    // stop once this call has returned
+    Stop,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Pause {
+    Entry,
+    Step,
+    Breakpoint,
+    Request,    // the front end asked for it while the program ran
+}
+
+pub struct Frame {
+    pub name: String,
+    pub line: usize,
+    pub environment: Rc<RefCell<Environment>>,
+}
+
+// What a front end sees and may change while the program is paused.
+pub struct DebugState {
+    pub breakpoints: BTreeSet<usize>,
+    pub frames: Vec<Frame>,     // innermost last; the first is the script itself
+}
+
+// Talks to the user of a debugger: asked what to do whenever the program pauses.
+pub trait Frontend {
+    fn paused(&mut self, state: &mut DebugState, reason: Pause) -> Resume;
+
+    // Called before every statement while the program runs; returning true pauses it.
+    fn poll(&mut self, _state: &mut DebugState) -> bool {
+        false
+    }
+
+    // Takes over program output, like `ExecutionHook::print`.
+    fn output(&mut self, _text: &str, _stream: Stream) -> bool {
+        false
+    }
+}
+
+// Decides where to pause and keeps the call stack. Only statements the parser
+// gave a line to can be paused at, and blocks are skipped in favour of what they hold.
+pub struct Debugger {
+    state: DebugState,
+    frontend: Box<dyn Frontend>,
+    lines: HashMap<Stmt, usize>,
+    mode: Resume,
+    from: usize,                            // call depth when the program was last resumed
+    last: Option<(usize, usize, bool)>,     // line, depth and whether it was an `if` or `while`
+    started: bool,
+    stop_on_entry: bool,
+}
+
+impl ExecutionHook for Debugger {
+    fn statement(&mut self, stmt: &Stmt, environment: &Rc<RefCell<Environment>>) -> Result<(), LoxError> {
+        if matches!(stmt, Stmt::Block(_)) {
+            return Ok(());
+        }
+        let Some(&line) = self.lines.get(stmt) else {
+            return Ok(());
+        };
+
+        let depth = self.state.frames.len();
+        if let Some(frame) = self.state.frames.last_mut() {
+            frame.line = line;
+            frame.environment = Rc::clone(environment);
+        }
+
+        // The body of an `if` or `while` written on the same line is part of the same stop.
+        let nested = matches!(self.last, Some((last_line, last_depth, true)) if last_line == line && last_depth == depth);
+        self.last = Some((line, depth, matches!(stmt, Stmt::If(_) | Stmt::While(_))));
+        if nested {
+            return Ok(());
+        }
+
+        let entry = !self.started;
+        self.started = true;
+        let reason = if entry && self.stop_on_entry {
+            Some(Pause::Entry)
+        } else if self.frontend.poll(&mut self.state) {
+            Some(Pause::Request)
+        } else if self.state.breakpoints.contains(&line) {
+            Some(Pause::Breakpoint)
+        } else {
+            match self.mode {
+                Resume::StepIn => Some(Pause::Step),
+                Resume::StepOver if depth <= self.from => Some(Pause::Step),
+                Resume::StepOut if depth < self.from => Some(Pause::Step),
+                _ => None,
+            }
+        };
+
+        if let Some(reason) = reason {
+            self.mode = self.frontend.paused(&mut self.state, reason);
+            self.from = depth;
+            if self.mode == Resume::Stop {
+                return Err(LoxError::Abort);
+            }
+        }
+        Ok(())
+    }
+
+    fn enter(&mut self, function: &dyn LoxCallable, paren: &Token, _arguments: &[Object]) {
+        let environment = Rc::clone(&self.state.frames.last().expect("The script frame is never popped.").environment);
+        self.state.frames.push(Frame { name: function.name().to_owned(), line: paren.line, environment });
+    }
+
+    fn leave(&mut self, _result: &Result<Object, LoxError>) {
+        if self.state.frames.len() > 1 {
+            self.state.frames.pop();
+        }
+    }
+
+    fn print(&mut self, text: &str, stream: Stream) -> bool {
+        self.frontend.output(text, stream)
+    }
+}
+
+impl Debugger {
+    pub fn new(lines: HashMap<Stmt, usize>, globals: &Rc<RefCell<Environment>>, frontend: Box<dyn Frontend>) -> Self {
+        let script = Frame { name: "<script>".to_owned(), line: 0, environment: Rc::clone(globals) };
+        Self {
+            state: DebugState { breakpoints: BTreeSet::new(), frames: vec![script] },
+            frontend,
+            lines,
+            mode: Resume::Continue,
+            from: 0,
+            last: None,
+            started: false,
+            stop_on_entry: true,
+        }
+    }
+
+    // Without a stop at entry the program runs until its first breakpoint.
+    pub fn stop_on_entry(mut self, stop: bool) -> Self {
+        self.stop_on_entry = stop;
+        self
+    }
+
+    pub fn breakpoints(mut self, lines: BTreeSet<usize>) -> Self {
+        self.state.breakpoints = lines;
+        self
+    }
+}
+
+// Values as a debugger shows them: strings are quoted so `"1"` and `1` can be told apart.
+pub fn describe(value: &Object) -> String {
+    match value {
+        Object::Str(x) => format!("{x:?}"),
+        value => value.to_string(),
+    }
+}
+
+// The scopes visible from an environment, innermost first, with a name for each.
+pub fn scopes(environment: &Rc<RefCell<Environment>>) -> Vec<(String, Rc<RefCell<Environment>>)> {
+    let mut chain = vec![Rc::clone(environment)];
+    while let Some(enclosing) = chain.last().and_then(|environment| environment.borrow().enclosing()) {
+        chain.push(enclosing);
+    }
+
+    let count = chain.len();
+    chain.into_iter().enumerate().map(|(i, environment)| {
+        let name = match i {
+            _ if i == count - 1 => "globals".to_owned(),
+            0 => "locals".to_owned(),
+            _ => format!("enclosing #{i}"),
+        };
+        (name, environment)
+    }).collect()
+}
+
+const HELP: &str = "\
+Commands:
+  c, continue       run until the next breakpoint
+  s, step           run to the next line, stepping into calls
+  n, next           run to the next line, stepping over calls
+  o, out            run until the current call returns
+  b, break <line>   set a breakpoint
+  d, delete <line>  remove a breakpoint
+  breakpoints       list breakpoints
+  p, print <name>   show a variable as seen from the current line
+  v, vars [frame]   show every scope of a frame, the innermost by default
+  bt, backtrace     show the call stack
+  l, list           show the source around the current line
+  q, quit           stop the program";
+
+// The front end of `rlox debug`: reads commands from a terminal.
+pub struct Console {
+    source: Vec<String>,
+}
+
+impl Frontend for Console {
+    fn paused(&mut self, state: &mut DebugState, reason: Pause) -> Resume {
+        let line = state.frames.last().map_or(0, |frame| frame.line);
+        match reason {
+            Pause::Entry => println!("Stopped at entry, line {line}. Type 'help' for commands."),
+            Pause::Step | Pause::Request => {},
+            Pause::Breakpoint => println!("Breakpoint at line {line}."),
+        }
+        self.show_line(line, true);
+
+        loop {
+            print!("(rlox) ");
+            let _ = io::stdout().flush();
+            let mut command = String::new();
+            match io::stdin().read_line(&mut command) {
+                // Nobody is left to give commands, so let the program run to its end.
+                Ok(0) | Err(_) => {
+                    state.breakpoints.clear();
+                    return Resume::Continue;
+                },
+                Ok(_) => {},
+            }
+
+            let words: Vec<&str> = command.split_whitespace().collect();
+            match words.as_slice() {
+                ["c" | "continue"] => return Resume::Continue,
+                ["s" | "step"] => return Resume::StepIn,
+                ["n" | "next"] => return Resume::StepOver,
+                ["o" | "out"] => return Resume::StepOut,
+                ["q" | "quit"] => return Resume::Stop,
+                ["b" | "break", line] => match line.parse() {
+                    Ok(line) => {
+                        state.breakpoints.insert(line);
+                        println!("Breakpoint set at line {line}.");
+                    },
+                    Err(_) => println!("Invalid line '{line}'."),
+                },
+                ["d" | "delete", line] => match line.parse() {
+                    Ok(line) if state.breakpoints.remove(&line) => println!("Breakpoint at line {line} removed."),
+                    _ => println!("No breakpoint at line {line}."),
+                },
+                ["breakpoints"] => {
+                    for line in &state.breakpoints {
+                        println!("  line {line}");
+                    }
+                },
+                ["p" | "print", name] => {
+                    let environment = &state.frames.last().expect("The script frame is never popped.").environment;
+                    match environment.borrow().lookup(name) {
+                        Some(value) => println!("{name} = {}", describe(&value)),
+                        None => println!("Undefined variable '{name}'."),
+                    }
+                },
+                ["v" | "vars", rest @ ..] => {
+                    let index = match rest {
+                        [] => Some(0),
+                        [n] => n.parse().ok(),
+                        _ => None,
+                    };
+                    match index.and_then(|n: usize| state.frames.iter().rev().nth(n)) {
+                        Some(frame) => Self::show_scopes(&frame.environment),
+                        None => println!("No such frame."),
+                    }
+                },
+                ["bt" | "backtrace"] => {
+                    for (i, frame) in state.frames.iter().rev().enumerate() {
+                        println!("#{i} {} at line {}", frame.name, frame.line);
+                    }
+                },
+                ["l" | "list"] => {
+                    for n in line.saturating_sub(3).max(1)..=line + 3 {
+                        self.show_line(n, n == line);
+                    }
+                },
+                ["h" | "help"] => println!("{HELP}"),
+                [] => {},
+                _ => println!("Unknown command '{}'. Type 'help' for commands.", command.trim()),
+            }
+        }
+    }
+}
+
+impl Console {
+    // Commands are read a line at a time through std's shared stdin buffer, so whatever the
+    // script reads between commands is left for it.
+    pub fn new(source: &str) -> Self {
+        Self { source: source.lines().map(str::to_owned).collect() }
+    }
+
+    fn show_line(&self, line: usize, current: bool) {
+        if let Some(text) = self.source.get(line.wrapping_sub(1)) {
+            println!("{} {line:>4} | {text}", if current { "->" } else { "  " });
+        }
+    }
+
+    fn show_scopes(environment: &Rc<RefCell<Environment>>) {
+        for (name, environment) in scopes(environment) {
+            println!("{name}:");
+            for (name, value) in environment.borrow().bindings() {
+                println!("  {name} = {}", describe(&value));
+            }
+        }
+    }
+}
@@ -1,7 +1,6 @@
 use std::cell::RefCell;
 use std::collections::HashMap;
 use std::collections::hash_map::Entry;
-use std::env;
 use std::rc::Rc;
 
 use crate::object::Object;
@@ -33,10 +32,29 @@ impl Environment {
         } else if let Some(enclosing) = &self.enclosing {
             enclosing.borrow().get(name)
         } else {
-            Err(LoxError::runtime_error(&name, &format!("Undifined variable '{}'.", name.lexeme)))
+            Err(LoxError::runtime_error(name, &format!("Undifined variable '{}'.", name.lexeme)))
         }
     } 
 
+    // Looks a name up without reporting anything when it is missing, for tools.
+    pub fn lookup(&self, name: &str) -> Option<Object> {
+        match self.values.get(name) {
+            Some(object) => Some(object.clone()),
+            None => self.enclosing.as_ref().and_then(|enclosing| enclosing.borrow().lookup(name)),
+        }
+    }
+
+    // The bindings of this scope alone, sorted by name.
+    pub fn bindings(&self) -> Vec<(String, Object)> {
+        let mut bindings: Vec<_> = self.values.iter().map(|(name, value)| (name.clone(), value.clone())).collect();
+        bindings.sort_by(|a, b| a.0.cmp(&b.0));
+        bindings
+    }
+
+    pub fn enclosing(&self) -> Option<Rc<RefCell<Environment>>> {
+        self.enclosing.clone()
+    }
+
     pub fn get_at(&self, distance: usize, name: &str) -> Result<Object, LoxError> {
         if distance == 0 {
             Ok(self.values.get(name).unwrap().clone())
@@ -47,9 +65,9 @@ impl Environment {
     }
 
     pub fn ancestor(&self, distance: usize) -> Rc<RefCell<Environment>> {
-        let mut environ = Rc::clone(&self.enclosing.as_ref().unwrap());
+        let mut environ = Rc::clone(self.enclosing.as_ref().unwrap());
         for _ in 1..distance {
-            let tmp = Rc::clone(&environ.borrow().enclosing.as_ref().unwrap());
+            let tmp = Rc::clone(environ.borrow().enclosing.as_ref().unwrap());
             environ = tmp;
         }
         Rc::clone(&environ)
@@ -62,7 +80,7 @@ impl Environment {
         } else if let Some(enclosing) = &self.enclosing {
             enclosing.borrow_mut().assign(name, value)
         } else {
-            Err(LoxError::runtime_error(&name, &format!("Undefined variable '{}'", name.lexeme)))
+            Err(LoxError::runtime_error(name, &format!("Undefined variable '{}'", name.lexeme)))
         }
     }
 
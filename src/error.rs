@@ -1,3 +1,5 @@
+use std::cell::RefCell;
+
 use crate::{token::{Token, TokenType}, object::Object};
 
 #[derive(Debug)]
@@ -5,15 +7,54 @@ pub enum LoxError {
     ScanError,
     ParseError,
     RuntimeError,
+    Runtime(Box<Token>, String),     // not reported yet; `Interpreter::interpret` reports it once output is flushed
     ObjectError(String),
     Break(usize),
     Return(Object, usize),
+    Abort,      // execution stopped from outside, e.g. by a debugger
 }
 
+// An error that was reported while `LoxError::capture` was running, instead of being printed.
+#[derive(Debug, Clone)]
+pub struct Diagnostic {
+    pub line: usize,
+    pub column: Option<usize>,      // where a scan error was found, in characters from 1
+    pub token: Option<Token>,
+    pub message: String,
+}
+
+thread_local! {
+    static CAPTURED: RefCell<Option<Vec<Diagnostic>>> = const { RefCell::new(None) };
+}
 
 impl LoxError {
     pub fn report(line: usize, locate: &str, message: &str) {
-        eprintln!("[line {}] Error {}: {}", line, locate, message);
+        Self::emit(line, None, None, locate, message);
+    }
+
+    // Runs `f` with every reported error collected rather than written to stderr.
+    pub fn capture<T>(f: impl FnOnce() -> T) -> (T, Vec<Diagnostic>) {
+        let previous = CAPTURED.with(|captured| captured.replace(Some(Vec::new())));
+        let result = f();
+        let diagnostics = CAPTURED.with(|captured| captured.replace(previous)).unwrap_or_default();
+        (result, diagnostics)
+    }
+
+    fn emit(line: usize, column: Option<usize>, token: Option<&Token>, locate: &str, message: &str) {
+        let captured = CAPTURED.with(|captured| match captured.borrow_mut().as_mut() {
+            Some(diagnostics) => {
+                diagnostics.push(Diagnostic { line, column, token: token.cloned(), message: message.to_owned() });
+                true
+            },
+            None => false,
+        });
+        if captured {
+            return;
+        }
+        match locate {
+            "" => eprintln!("[line {}] Error: {}", line, message),
+            _ => eprintln!("[line {}] Error {}: {}", line, locate, message),
+        }
     }
 
     pub fn scan_error(line: usize, message: &str) -> Self {
@@ -21,10 +62,16 @@ impl LoxError {
         Self::ScanError
     }
 
+    // For scan errors inside a token, such as a bad escape in a string.
+    pub fn scan_error_at(line: usize, column: usize, message: &str) -> Self {
+        Self::emit(line, Some(column), None, &format!("at column {column}"), message);
+        Self::ScanError
+    }
+
     pub fn parse_error(token: &Token, message: &str) -> Self {
         match token.ttype {
-            TokenType::Eof => Self::report(token.line, "at end", message),
-            _ => Self::report(token.line, &format!("at '{}'", &token.lexeme), message),
+            TokenType::Eof => Self::emit(token.line, None, Some(token), "at end", message),
+            _ => Self::emit(token.line, None, Some(token), &format!("at '{}'", &token.lexeme), message),
         }
         LoxError::ParseError
     }
@@ -34,8 +81,18 @@ impl LoxError {
     }
 
     pub fn runtime_error(token: &Token, message: &str) -> Self {
-        Self::report(token.line, "", message);
-        Self::RuntimeError
+        Self::Runtime(Box::new(token.clone()), message.to_owned())
     }
 
+    pub fn report_runtime(token: &Token, message: &str) {
+        Self::emit(token.line, None, Some(token), "", message);
+    }
+
+    // Object errors carry no position, so report them at the token that triggered them.
+    pub fn at(self, token: &Token) -> Self {
+        match self {
+            Self::ObjectError(message) => Self::runtime_error(token, &message),
+            e => e,
+        }
+    }
 }
@@ -10,17 +10,20 @@ pub trait ExprVisitor {
     fn visit_binary_expr(&mut self, expr: Rc<BinaryExpr>) -> Result<Self::Output, LoxError>;
     fn visit_call_expr(&mut self, expr: Rc<CallExpr>) -> Result<Self::Output, LoxError>;
     fn visit_grouping_expr(&mut self, expr: Rc<GroupingExpr>) -> Result<Self::Output, LoxError>;
+    fn visit_interpolation_expr(&mut self, expr: Rc<InterpolationExpr>) -> Result<Self::Output, LoxError>;
     fn visit_literal_expr(&mut self, expr: Rc<LiteralExpr>) -> Result<Self::Output, LoxError>;
     fn visit_logical_expr(&mut self, expr: Rc<LogicalExpr>) -> Result<Self::Output, LoxError>;
     fn visit_unary_expr(&mut self, expr: Rc<UnaryExpr>) -> Result<Self::Output, LoxError>;
     fn visit_variable_expr(&mut self, expr: Rc<VariableExpr>) -> Result<Self::Output, LoxError>;
 }
 
+#[derive(Clone)]
 pub enum Expr {
     Assign(Rc<AssignExpr>),
     Binary(Rc<BinaryExpr>),
     Call(Rc<CallExpr>),
     Grouping(Rc<GroupingExpr>),
+    Interpolation(Rc<InterpolationExpr>),
     Literal(Rc<LiteralExpr>),
     Logical(Rc<LogicalExpr>),
     Unary(Rc<UnaryExpr>),
@@ -48,6 +51,10 @@ pub struct GroupingExpr {
     pub expression: Box<Expr>,
 }
 
+pub struct InterpolationExpr {
+    pub parts: Vec<Expr>,
+}
+
 pub struct LiteralExpr {
     pub value: Option<Object>,
 }
@@ -74,6 +81,7 @@ impl Expr {
             Expr::Binary(binarystmt) => binarystmt.accept(visitor),
             Expr::Call(callstmt) => callstmt.accept(visitor),
             Expr::Grouping(groupingstmt) => groupingstmt.accept(visitor),
+            Expr::Interpolation(interpolationstmt) => interpolationstmt.accept(visitor),
             Expr::Literal(literalstmt) => literalstmt.accept(visitor),
             Expr::Logical(logicalstmt) => logicalstmt.accept(visitor),
             Expr::Unary(unarystmt) => unarystmt.accept(visitor),
@@ -89,6 +97,7 @@ impl PartialEq for Expr {
             (Expr::Binary(a), Expr::Binary(b)) => Rc::ptr_eq(a, b),
             (Expr::Call(a), Expr::Call(b)) => Rc::ptr_eq(a, b),
             (Expr::Grouping(a), Expr::Grouping(b)) => Rc::ptr_eq(a, b),
+            (Expr::Interpolation(a), Expr::Interpolation(b)) => Rc::ptr_eq(a, b),
             (Expr::Literal(a), Expr::Literal(b)) => Rc::ptr_eq(a, b),
             (Expr::Logical(a), Expr::Logical(b)) => Rc::ptr_eq(a, b),
             (Expr::Unary(a), Expr::Unary(b)) => Rc::ptr_eq(a, b),
@@ -107,6 +116,7 @@ impl Hash for Expr {
             Expr::Binary(a) => hasher.write_usize(Rc::as_ptr(a) as usize),
             Expr::Call(a) => hasher.write_usize(Rc::as_ptr(a) as usize),
             Expr::Grouping(a) => hasher.write_usize(Rc::as_ptr(a) as usize),
+            Expr::Interpolation(a) => hasher.write_usize(Rc::as_ptr(a) as usize),
             Expr::Literal(a) => hasher.write_usize(Rc::as_ptr(a) as usize),
             Expr::Logical(a) => hasher.write_usize(Rc::as_ptr(a) as usize),
             Expr::Unary(a) => hasher.write_usize(Rc::as_ptr(a) as usize),
@@ -143,6 +153,13 @@ impl GroupingExpr {
 
 }
 
+impl InterpolationExpr {
+    pub fn accept<U>(self: &Rc<InterpolationExpr>, visitor: &mut impl ExprVisitor<Output = U>) -> Result<U, LoxError> {
+        visitor.visit_interpolation_expr(Rc::clone(self))
+    }
+
+}
+
 impl LiteralExpr {
     pub fn accept<U>(self: &Rc<LiteralExpr>, visitor: &mut impl ExprVisitor<Output = U>) -> Result<U, LoxError> {
         visitor.visit_literal_expr(Rc::clone(self))
@@ -0,0 +1,159 @@
+use std::{fs, io::{self, BufRead, Write}, path::{Path, PathBuf}};
+
+use crate::{callable::NativeFunction, interpreter::Interpreter, object::Object, error::LoxError, natives};
+
+// The directories file natives may read and write, with everything beneath them. Nothing is
+// granted by default; `rlox` grants what `--allow-read=dir` and `--allow-write=dir` name, and
+// embedders call `allow_read` and `allow_write`. Paths are resolved, symbolic links included,
+// before they are checked, so `..` and links cannot lead out of a granted directory.
+#[derive(Debug, Clone, Default)]
+pub struct Permissions {
+    read: Vec<PathBuf>,
+    write: Vec<PathBuf>,
+}
+
+#[derive(Clone, Copy)]
+enum Access {
+    Read,
+    Write,
+}
+
+impl Permissions {
+    pub fn allow_read(&mut self, dir: impl AsRef<Path>) -> io::Result<()> {
+        self.read.push(fs::canonicalize(dir)?);
+        Ok(())
+    }
+
+    pub fn allow_write(&mut self, dir: impl AsRef<Path>) -> io::Result<()> {
+        self.write.push(fs::canonicalize(dir)?);
+        Ok(())
+    }
+
+    pub fn any(&self) -> bool {
+        !self.read.is_empty() || !self.write.is_empty()
+    }
+
+    // The resolved path, if `function` may touch it. A path that does not exist yet is resolved
+    // through its parent directory, so files can be created. A link whose target does not exist
+    // is refused, as writing through it would create a file wherever it points.
+    fn check(&self, path: &str, access: Access, function: &str) -> Result<PathBuf, LoxError> {
+        let resolved = fs::canonicalize(path).or_else(|e| {
+            let parent = Path::new(path).parent().filter(|parent| !parent.as_os_str().is_empty()).unwrap_or(Path::new("."));
+            match Path::new(path).file_name() {
+                Some(name) => fs::canonicalize(parent).map(|parent| parent.join(name)),
+                None => Err(e),
+            }
+        }).map_err(|e| failure(function, path, e))?;
+        if fs::symlink_metadata(&resolved).is_ok_and(|metadata| metadata.file_type().is_symlink()) {
+            return Err(LoxError::object_error(&format!("Function '{function}' cannot follow the broken link '{path}'.")));
+        }
+
+        let (granted, verb, flag) = match access {
+            Access::Read => (&self.read, "read", "--allow-read"),
+            Access::Write => (&self.write, "write", "--allow-write"),
+        };
+        match granted.iter().any(|dir| resolved.starts_with(dir)) {
+            true => Ok(resolved),
+            false => Err(LoxError::object_error(&format!("Function '{function}' is not allowed to {verb} '{path}'; grant it with {flag}."))),
+        }
+    }
+}
+
+fn failure(function: &str, path: &str, e: io::Error) -> LoxError {
+    LoxError::object_error(&format!("Function '{function}' failed on '{path}': {e}."))
+}
+
+pub const FILES: &[NativeFunction] = &[
+    NativeFunction { name: "readFile", arity: 1, function: read_file },
+    NativeFunction { name: "eachLine", arity: 2, function: each_line },
+    NativeFunction { name: "writeFile", arity: 2, function: write_file },
+    NativeFunction { name: "appendFile", arity: 2, function: append_file },
+    NativeFunction { name: "fileExists", arity: 1, function: file_exists },
+    NativeFunction { name: "listDir", arity: 2, function: list_dir },
+    NativeFunction { name: "deleteFile", arity: 1, function: delete_file },
+];
+
+fn path_argument<'a>(arguments: &'a [Object], function: &str) -> Result<&'a str, LoxError> {
+    match &arguments[0] {
+        Object::Str(path) => Ok(path),
+        _ => Err(LoxError::object_error(&format!("Function '{function}' need a path string."))),
+    }
+}
+
+// A path and the text to put in it.
+fn write_arguments<'a>(arguments: &'a [Object], function: &str) -> Result<(&'a str, &'a str), LoxError> {
+    match arguments {
+        [Object::Str(path), Object::Str(text)] => Ok((path, text)),
+        _ => Err(LoxError::object_error(&format!("Function '{function}' need a path and a string."))),
+    }
+}
+
+fn read_file(interpreter: &mut Interpreter, arguments: &[Object]) -> Result<Object, LoxError> {
+    let path = path_argument(arguments, "readFile")?;
+    let resolved = interpreter.permissions.check(path, Access::Read, "readFile")?;
+    fs::read_to_string(resolved).map(Object::Str).map_err(|e| failure("readFile", path, e))
+}
+
+// Calls a function with each line, without its line break, reading as it goes.
+fn each_line(interpreter: &mut Interpreter, arguments: &[Object]) -> Result<Object, LoxError> {
+    let path = path_argument(arguments, "eachLine")?;
+    let resolved = interpreter.permissions.check(path, Access::Read, "eachLine")?;
+    let file = fs::File::open(resolved).map_err(|e| failure("eachLine", path, e))?;
+    let lines = io::BufReader::new(file).lines().map(|line| line.map(Object::Str).map_err(|e| failure("eachLine", path, e)));
+    natives::each(interpreter, &arguments[1], "eachLine", lines)
+}
+
+// Opens a checked path. A file that was not there is made with `create_new`, which will not
+// follow a link put in its place since the check.
+fn open_for_write(path: &Path, append: bool) -> io::Result<fs::File> {
+    let mut options = fs::OpenOptions::new();
+    match fs::symlink_metadata(path) {
+        Ok(_) => options.write(true).append(append).truncate(!append),
+        Err(_) => options.write(true).create_new(true),
+    };
+    options.open(path)
+}
+
+// Creates the file, or replaces what it held.
+fn write_file(interpreter: &mut Interpreter, arguments: &[Object]) -> Result<Object, LoxError> {
+    let (path, text) = write_arguments(arguments, "writeFile")?;
+    let resolved = interpreter.permissions.check(path, Access::Write, "writeFile")?;
+    open_for_write(&resolved, false)
+        .and_then(|mut file| file.write_all(text.as_bytes()))
+        .map(|_| Object::Nil)
+        .map_err(|e| failure("writeFile", path, e))
+}
+
+// Creates the file, or adds to its end.
+fn append_file(interpreter: &mut Interpreter, arguments: &[Object]) -> Result<Object, LoxError> {
+    let (path, text) = write_arguments(arguments, "appendFile")?;
+    let resolved = interpreter.permissions.check(path, Access::Write, "appendFile")?;
+    open_for_write(&resolved, true)
+        .and_then(|mut file| file.write_all(text.as_bytes()))
+        .map(|_| Object::Nil)
+        .map_err(|e| failure("appendFile", path, e))
+}
+
+fn file_exists(interpreter: &mut Interpreter, arguments: &[Object]) -> Result<Object, LoxError> {
+    let path = path_argument(arguments, "fileExists")?;
+    let resolved = interpreter.permissions.check(path, Access::Read, "fileExists")?;
+    Ok(Object::Bool(resolved.exists()))
+}
+
+// Calls a function with the name of each entry of a directory, in sorted order.
+fn list_dir(interpreter: &mut Interpreter, arguments: &[Object]) -> Result<Object, LoxError> {
+    let path = path_argument(arguments, "listDir")?;
+    let resolved = interpreter.permissions.check(path, Access::Read, "listDir")?;
+    let mut names = fs::read_dir(resolved)
+        .and_then(|entries| entries.map(|entry| Ok(entry?.file_name().to_string_lossy().into_owned())).collect::<io::Result<Vec<_>>>())
+        .map_err(|e| failure("listDir", path, e))?;
+    names.sort();
+    natives::each(interpreter, &arguments[1], "listDir", names.into_iter().map(|name| Ok(Object::Str(name))))
+}
+
+// Files only; directories stay.
+fn delete_file(interpreter: &mut Interpreter, arguments: &[Object]) -> Result<Object, LoxError> {
+    let path = path_argument(arguments, "deleteFile")?;
+    let resolved = interpreter.permissions.check(path, Access::Write, "deleteFile")?;
+    fs::remove_file(resolved).map(|_| Object::Nil).map_err(|e| failure("deleteFile", path, e))
+}
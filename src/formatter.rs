@@ -0,0 +1,318 @@
+use std::rc::Rc;
+
+use crate::{expr::*, stmt::*, error::LoxError, object::Object, parser::{Trivia, ForClauses}, scanner::Comment, token::{Token, TokenType}};
+
+// Prints a parsed program back as canonical Lox: one statement per line, braces on the
+// line that opens them and `indent` spaces per level. Comments are placed back before the
+// statement that follows them, or at the end of the line when they trailed a statement.
+pub struct Formatter<'a> {
+    indent: usize,
+    depth: usize,
+    output: String,
+    tokens: &'a [Token],
+    comments: &'a [Comment],
+    next_comment: usize,
+    last_line: usize,
+    fresh: bool,            // nothing written yet in the current block
+    trivia: &'a Trivia,
+}
+
+impl<'a> ExprVisitor for Formatter<'a> {
+    type Output = String;
+
+    fn visit_assign_expr(&mut self, expr: Rc<AssignExpr>) -> Result<Self::Output, LoxError> {
+        Ok(format!("{} = {}", expr.name.lexeme, expr.value.accept(self)?))
+    }
+
+    fn visit_binary_expr(&mut self, expr: Rc<BinaryExpr>) -> Result<Self::Output, LoxError> {
+        Ok(format!("{} {} {}", expr.left.accept(self)?, expr.operator.lexeme, expr.right.accept(self)?))
+    }
+
+    fn visit_call_expr(&mut self, expr: Rc<CallExpr>) -> Result<Self::Output, LoxError> {
+        let arguments = expr.arguments.iter()
+            .map(|argument| argument.accept(self))
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(format!("{}({})", expr.callee.accept(self)?, arguments.join(", ")))
+    }
+
+    fn visit_grouping_expr(&mut self, expr: Rc<GroupingExpr>) -> Result<Self::Output, LoxError> {
+        Ok(format!("({})", expr.expression.accept(self)?))
+    }
+
+    // The string segments print as written, `"a ${`, `} b ${` and `} c"`, with the expressions between.
+    fn visit_interpolation_expr(&mut self, expr: Rc<InterpolationExpr>) -> Result<Self::Output, LoxError> {
+        expr.parts.iter().map(|part| part.accept(self)).collect()
+    }
+
+    // Literals from the source are kept as written, so `0xFF` stays `0xFF`.
+    fn visit_literal_expr(&mut self, expr: Rc<LiteralExpr>) -> Result<Self::Output, LoxError> {
+        let token = self.trivia.expr_spans.get(&Expr::Literal(Rc::clone(&expr))).and_then(|(start, _)| self.tokens.get(*start));
+        if let Some(token) = token.filter(|token| matches!(token.ttype, TokenType::Number | TokenType::String | TokenType::Interpolation | TokenType::InterpolationEnd)) {
+            return Ok(token.lexeme.clone());
+        }
+        match &expr.value {
+            Some(Object::Str(value)) => Ok(format!("\"{value}\"")),
+            Some(value) => Ok(value.to_string()),
+            None => Ok("nil".to_owned()),
+        }
+    }
+
+    fn visit_logical_expr(&mut self, expr: Rc<LogicalExpr>) -> Result<Self::Output, LoxError> {
+        Ok(format!("{} {} {}", expr.left.accept(self)?, expr.operator.lexeme, expr.right.accept(self)?))
+    }
+
+    fn visit_unary_expr(&mut self, expr: Rc<UnaryExpr>) -> Result<Self::Output, LoxError> {
+        Ok(format!("{}{}", expr.operator.lexeme, expr.right.accept(self)?))
+    }
+
+    fn visit_variable_expr(&mut self, expr: Rc<VariableExpr>) -> Result<Self::Output, LoxError> {
+        Ok(expr.name.lexeme.clone())
+    }
+}
+
+// Statements write their text straight into the output, without the leading indent.
+impl<'a> StmtVisitor for Formatter<'a> {
+    type Output = ();
+
+    fn visit_break_stmt(&mut self, _stmt: Rc<BreakStmt>) -> Result<Self::Output, LoxError> {
+        self.output.push_str("break;");
+        Ok(())
+    }
+
+    fn visit_block_stmt(&mut self, stmt: Rc<BlockStmt>) -> Result<Self::Output, LoxError> {
+        let closing = self.stmt_span(&Stmt::Block(Rc::clone(&stmt))).map(|(_, end)| end);
+        self.block(&stmt.statements, closing)
+    }
+
+    fn visit_expression_stmt(&mut self, stmt: Rc<ExpressionStmt>) -> Result<Self::Output, LoxError> {
+        let expression = stmt.expression.accept(self)?;
+        self.output.push_str(&format!("{expression};"));
+        Ok(())
+    }
+
+    fn visit_function_stmt(&mut self, stmt: Rc<FunctionStmt>) -> Result<Self::Output, LoxError> {
+        let parameters: Vec<&str> = stmt.parameters.iter().map(|param| param.lexeme.as_str()).collect();
+        self.output.push_str(&format!("fun {}({}) ", stmt.name.lexeme, parameters.join(", ")));
+        let closing = self.stmt_span(&Stmt::Function(Rc::clone(&stmt))).map(|(_, end)| end);
+        self.block(&stmt.body, closing)
+    }
+
+    fn visit_if_stmt(&mut self, stmt: Rc<IfStmt>) -> Result<Self::Output, LoxError> {
+        let condition = stmt.condition.accept(self)?;
+        self.output.push_str(&format!("if ({condition})"));
+        self.body(&stmt.then_branch)?;
+
+        if let Some(else_branch) = &stmt.else_branch {
+            if self.output.ends_with('\n') {
+                self.write_indent();
+                self.output.push_str("else");
+            } else {
+                self.output.push_str(" else");
+            }
+
+            match else_branch.as_ref() {
+                Stmt::If(_) => {
+                    self.output.push(' ');
+                    self.content(else_branch)?;
+                },
+                _ => self.body(else_branch)?,
+            }
+        }
+        Ok(())
+    }
+
+    fn visit_print_stmt(&mut self, stmt: Rc<PrintStmt>) -> Result<Self::Output, LoxError> {
+        let expression = stmt.expression.accept(self)?;
+        self.output.push_str(&format!("print {expression};"));
+        Ok(())
+    }
+
+    fn visit_return_stmt(&mut self, stmt: Rc<ReturnStmt>) -> Result<Self::Output, LoxError> {
+        match &stmt.value {
+            Expr::Literal(literal) if matches!(literal.value, Some(Object::Nil)) => self.output.push_str("return;"),
+            value => {
+                let value = value.accept(self)?;
+                self.output.push_str(&format!("return {value};"));
+            }
+        }
+        Ok(())
+    }
+
+    fn visit_var_stmt(&mut self, stmt: Rc<VarStmt>) -> Result<Self::Output, LoxError> {
+        match &stmt.initializer {
+            Some(initializer) => {
+                let initializer = initializer.accept(self)?;
+                self.output.push_str(&format!("var {} = {initializer};", stmt.name.lexeme));
+            },
+            None => self.output.push_str(&format!("var {};", stmt.name.lexeme)),
+        }
+        Ok(())
+    }
+
+    fn visit_while_stmt(&mut self, stmt: Rc<WhileStmt>) -> Result<Self::Output, LoxError> {
+        let condition = stmt.condition.accept(self)?;
+        self.output.push_str(&format!("while ({condition})"));
+        self.body(&stmt.body)
+    }
+}
+
+impl<'a> Formatter<'a> {
+    pub fn new(indent: usize, tokens: &'a [Token], comments: &'a [Comment], trivia: &'a Trivia) -> Self {
+        Self { indent, depth: 0, output: String::new(), tokens, comments, next_comment: 0, last_line: 0, fresh: true, trivia }
+    }
+
+    pub fn format(mut self, statements: &[Stmt]) -> Result<String, LoxError> {
+        for stmt in statements {
+            self.statement(stmt)?;
+        }
+        self.comments_before(usize::MAX);
+        Ok(self.output)
+    }
+
+    fn statement(&mut self, stmt: &Stmt) -> Result<(), LoxError> {
+        let span = self.stmt_span(stmt);
+        if let Some((first, _)) = span {
+            self.comments_before(first);
+            self.blank_line(self.tokens[first].line);
+        }
+
+        self.write_indent();
+        self.content(stmt)?;
+
+        if let Some((_, last)) = span {
+            if !self.output.ends_with('\n') {
+                self.trailing_comments(last);
+            }
+            self.last_line = self.tokens[last].line;
+        }
+        if !self.output.ends_with('\n') {
+            self.output.push('\n');
+        }
+        self.fresh = false;
+        Ok(())
+    }
+
+    fn content(&mut self, stmt: &Stmt) -> Result<(), LoxError> {
+        match self.trivia.for_loops.get(stmt) {
+            Some(clauses) => self.for_loop(stmt, clauses),
+            None => stmt.accept(self),
+        }
+    }
+
+    // The body of `if`, `while` and `for`: blocks stay on the same line, anything else is indented below.
+    fn body(&mut self, stmt: &Stmt) -> Result<(), LoxError> {
+        match stmt {
+            Stmt::Block(block) if !self.trivia.for_loops.contains_key(stmt) => {
+                self.output.push(' ');
+                self.visit_block_stmt(Rc::clone(block))
+            },
+            _ => {
+                self.output.push('\n');
+                self.depth += 1;
+                self.fresh = true;
+                let res = self.statement(stmt);
+                self.depth -= 1;
+                res
+            }
+        }
+    }
+
+    fn block(&mut self, statements: &[Stmt], closing: Option<usize>) -> Result<(), LoxError> {
+        let has_comments = matches!((self.comments.get(self.next_comment), closing), (Some(comment), Some(closing)) if comment.token <= closing);
+        if statements.is_empty() && !has_comments {
+            self.output.push_str("{}");
+            return Ok(());
+        }
+
+        self.output.push_str("{\n");
+        self.depth += 1;
+        self.fresh = true;
+        for stmt in statements {
+            self.statement(stmt)?;
+        }
+        if let Some(closing) = closing {
+            self.comments_before(closing);
+            self.last_line = self.tokens[closing].line;
+        }
+        self.depth -= 1;
+        self.write_indent();
+        self.output.push('}');
+        Ok(())
+    }
+
+    // Undoes the desugaring done by `Parser::for_statement`.
+    fn for_loop(&mut self, stmt: &Stmt, clauses: &ForClauses) -> Result<(), LoxError> {
+        let (initializer, loop_stmt) = match stmt {
+            Stmt::Block(block) if clauses.initializer => (Some(&block.statements[0]), &block.statements[1]),
+            _ => (None, stmt),
+        };
+        let Stmt::While(loop_stmt) = loop_stmt else { unreachable!() };
+
+        let (body, increment) = match loop_stmt.body.as_ref() {
+            Stmt::Block(block) if clauses.increment => match &block.statements[1] {
+                Stmt::Expression(increment) => (&block.statements[0], Some(&increment.expression)),
+                _ => unreachable!(),
+            },
+            body => (body, None),
+        };
+
+        self.output.push_str("for (");
+        match initializer {
+            Some(initializer) => initializer.accept(self)?,
+            None => self.output.push(';'),
+        }
+        if clauses.condition {
+            let condition = loop_stmt.condition.accept(self)?;
+            self.output.push_str(&format!(" {condition};"));
+        } else {
+            self.output.push(';');
+        }
+        if let Some(increment) = increment {
+            let increment = increment.accept(self)?;
+            self.output.push_str(&format!(" {increment}"));
+        }
+        self.output.push(')');
+        self.body(body)
+    }
+
+    fn comments_before(&mut self, token: usize) {
+        while let Some(comment) = self.comments.get(self.next_comment) {
+            if comment.token > token {
+                break;
+            }
+            self.blank_line(comment.line);
+            self.write_indent();
+            self.output.push_str(&comment.text.replace("\r\n", "\n"));
+            self.output.push('\n');
+            self.last_line = comment.line + comment.text.matches('\n').count();
+            self.fresh = false;
+            self.next_comment += 1;
+        }
+    }
+
+    fn trailing_comments(&mut self, last: usize) {
+        while let Some(comment) = self.comments.get(self.next_comment) {
+            if comment.token != last + 1 || !comment.trailing {
+                break;
+            }
+            self.output.push(' ');
+            self.output.push_str(&comment.text.replace("\r\n", "\n"));
+            self.next_comment += 1;
+        }
+    }
+
+    // Keeps at most one of the blank lines the source had between two statements.
+    fn blank_line(&mut self, line: usize) {
+        if !self.fresh && line > self.last_line + 1 {
+            self.output.push('\n');
+        }
+    }
+
+    fn write_indent(&mut self) {
+        self.output.push_str(&" ".repeat(self.indent * self.depth));
+    }
+
+    fn stmt_span(&self, stmt: &Stmt) -> Option<(usize, usize)> {
+        self.trivia.spans.get(stmt).copied()
+    }
+}
@@ -0,0 +1,51 @@
+use std::{cell::RefCell, io, rc::Rc};
+
+use crate::{stmt::Stmt, expr::Expr, object::Object, token::Token, error::LoxError, environment::Environment, callable::LoxCallable};
+
+// A place where execution goes one of two ways: an `if` or `while` statement, or an `and`/`or` expression.
+#[derive(Clone, PartialEq, Eq, Hash)]
+pub enum Branch {
+    Stmt(Stmt),
+    Expr(Expr),
+}
+
+// Which of the program's two output streams text was written to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Stream {
+    Stdout,
+    Stderr,
+}
+
+// Observes the interpreter as it runs, for debuggers and other tools. Every method does
+// nothing by default. An error returned from `statement` stops the program.
+pub trait ExecutionHook {
+    // Called before each statement runs, with the environment it runs in.
+    fn statement(&mut self, _stmt: &Stmt, _environment: &Rc<RefCell<Environment>>) -> Result<(), LoxError> {
+        Ok(())
+    }
+
+    // Called when a call starts, after its arguments were evaluated. `paren` is the call site.
+    fn enter(&mut self, _function: &dyn LoxCallable, _paren: &Token, _arguments: &[Object]) {}
+
+    // Called when the call that last entered returns, normally or with an error.
+    fn leave(&mut self, _result: &Result<Object, LoxError>) {}
+
+    // Called after an assignment, with how many scopes out the variable was resolved, `None` for globals.
+    fn assign(&mut self, _name: &Token, _value: &Object, _depth: Option<usize>) {}
+
+    // Called each time a branch is decided: `taken` is true when an `if` runs its then branch,
+    // a `while` runs its body, or a logical operator evaluates its right operand.
+    fn branch(&mut self, _site: &Branch, _taken: bool) {}
+
+    // Called with the text `print` and `write` produce, and with what `eprint` writes to
+    // `Stream::Stderr`, line breaks included. Returning true keeps it from reaching the
+    // interpreter's output.
+    fn print(&mut self, _text: &str, _stream: Stream) -> bool {
+        false
+    }
+
+    // Called once the program is over, to write out whatever was recorded.
+    fn finish(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
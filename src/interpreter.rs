@@ -1,13 +1,22 @@
 use crate::loxfunction::LoxFunction;
-use crate::{object::Object, expr::*, error::LoxError, token::*, stmt::*, callable::*, environment::Environment};
+use crate::natives;
+use crate::random::Random;
+use crate::files::{self, Permissions};
+use crate::{object::Object, expr::*, error::LoxError, token::*, stmt::*, callable::*, environment::Environment, hook::{ExecutionHook, Branch, Stream}};
 use std::collections::HashMap;
 use std::rc::Rc;
 use std::cell::RefCell;
+use std::io::{self, BufRead, Write};
 
 pub struct Interpreter {
     pub globals: Rc<RefCell<Environment>>,
     environment: Rc<RefCell<Environment>>,
     locals: HashMap<Expr, usize>,
+    hook: Option<Box<dyn ExecutionHook>>,
+    pub random: Random,     // shared by the random natives, reseeded by `seed`
+    pub permissions: Permissions,   // what the file natives may touch
+    input: Option<Box<dyn BufRead>>,    // what `readLine` and friends read, standard input if `None`
+    output: Box<dyn Write>,     // where `print` and `write` go
 }
 
 impl ExprVisitor for Interpreter {
@@ -28,12 +37,14 @@ impl ExprVisitor for Interpreter {
                     &format!("Expected {} arguments but got {}.",
                     function.arity(), arguments.len())))
             } else {
-                function.call(self, &arguments).map_or_else(|e| {
-                    if let LoxError::ObjectError(message) = &e {
-                        LoxError::report(expr.paren.line, "", message);
-                    }
-                    Err(e)
-                }, Ok)
+                if let Some(hook) = &mut self.hook {
+                    hook.enter(function.as_ref(), &expr.paren, &arguments);
+                }
+                let result = function.call(self, &arguments).map_err(|e| e.at(&expr.paren));
+                if let Some(hook) = &mut self.hook {
+                    hook.leave(&result);
+                }
+                result
             }
         } else {
             Err(LoxError::runtime_error(&expr.paren, "Can only call functions and classes."))
@@ -43,20 +54,28 @@ impl ExprVisitor for Interpreter {
     fn visit_logical_expr(&mut self, expr: Rc<LogicalExpr>) -> Result<Self::Output, LoxError> {
         let left = self.evaluate(&expr.left)?;
 
-        match expr.operator.ttype {
-            TokenType::Or if Self::is_truthy(&left) => Ok(left),
-            TokenType::And if !Self::is_truthy(&left) => Ok(left),
-            _ => self.evaluate(&expr.right)
+        let short_circuit = match expr.operator.ttype {
+            TokenType::Or => Self::is_truthy(&left),
+            _ => !Self::is_truthy(&left),
+        };
+        self.branch(|| Branch::Expr(Expr::Logical(Rc::clone(&expr))), !short_circuit);
+        match short_circuit {
+            true => Ok(left),
+            false => self.evaluate(&expr.right),
         }
     }
 
     fn visit_assign_expr(&mut self, expr: Rc<AssignExpr>) -> Result<Self::Output, LoxError> {
         let value = self.evaluate(&expr.value)?;
-        if let Some(distance) = self.locals.get(&Expr::Assign(Rc::clone(&expr))) {
-            self.environment.borrow_mut().assign_at(*distance, &expr.name, value.clone())?;
+        let depth = self.locals.get(&Expr::Assign(Rc::clone(&expr))).copied();
+        if let Some(distance) = depth {
+            self.environment.borrow_mut().assign_at(distance, &expr.name, value.clone())?;
         } else {
             self.globals.borrow_mut().assign(&expr.name, value.clone())?;
         }
+        if let Some(hook) = &mut self.hook {
+            hook.assign(&expr.name, &value, depth);
+        }
         Ok(value)
     }
 
@@ -69,6 +88,14 @@ impl ExprVisitor for Interpreter {
             TokenType::Minus => left - right,
             TokenType::Star => left * right,
             TokenType::Slash => left / right,
+            TokenType::Percent => left % right,
+            TokenType::StarStar => left.power(right),
+            TokenType::TildeSlash => left.floor_div(right),
+            TokenType::Ampersand => left.bit_and(right),
+            TokenType::Pipe => left.bit_or(right),
+            TokenType::Caret => left.bit_xor(right),
+            TokenType::LessLess => left.shift_left(right),
+            TokenType::GreaterGreater => left.shift_right(right),
             TokenType::Greater => left.greater(right),
             TokenType::GreaterEqual => left.greaterequal(right),
             TokenType::Less => left.less(right),
@@ -79,16 +106,21 @@ impl ExprVisitor for Interpreter {
             _ => Err(LoxError::runtime_error(&expr.operator, &format!("Unexpected operator '{}' in binary expression.", expr.operator.lexeme)))
         };
         
-        if let Err(LoxError::ObjectError(message)) = &result {
-            LoxError::report(expr.operator.line, "", message);
-        }
-        result
+        result.map_err(|e| e.at(&expr.operator))
     }   
 
     fn visit_grouping_expr(&mut self, expr: Rc<GroupingExpr>) -> Result<Self::Output, LoxError> {
         self.evaluate(&expr.expression)
     }
 
+    fn visit_interpolation_expr(&mut self, expr: Rc<InterpolationExpr>) -> Result<Self::Output, LoxError> {
+        let mut text = String::new();
+        for part in &expr.parts {
+            text += &self.evaluate(part)?.to_string();
+        }
+        Ok(Object::Str(text))
+    }
+
     fn visit_literal_expr(&mut self, expr: Rc<LiteralExpr>) -> Result<Self::Output, LoxError> {
         expr.value.clone().ok_or_else(|| LoxError::object_error("There is no valid literal!"))
     }
@@ -98,11 +130,14 @@ impl ExprVisitor for Interpreter {
         // a kind of awkward
         let right = self.evaluate(&expr.right)?;
 
-        match expr.operator.ttype {
+        let result = match expr.operator.ttype {
             TokenType::Minus => - right,
             TokenType::Bang => ! right,
+            TokenType::Tilde => right.bit_not(),
             _ => Err(LoxError::runtime_error(&expr.operator, "Cannot use operator like unary."))
-        }
+        };
+
+        result.map_err(|e| e.at(&expr.operator))
     }
 
     fn visit_variable_expr(&mut self, expr: Rc<VariableExpr>) -> Result<Self::Output, LoxError> {
@@ -129,7 +164,12 @@ impl StmtVisitor for Interpreter {
     }
 
     fn visit_while_stmt(&mut self, stmt: Rc<WhileStmt>) -> Result<Self::Output, LoxError> {
-        while Self::is_truthy(&self.evaluate(&stmt.condition)?) {
+        loop {
+            let taken = Self::is_truthy(&self.evaluate(&stmt.condition)?);
+            self.branch(|| Branch::Stmt(Stmt::While(Rc::clone(&stmt))), taken);
+            if !taken {
+                break;
+            }
             match self.execute(&stmt.body) {
                 Err(LoxError::Break(_)) => break,
                 Err(e) => return Err(e),
@@ -140,7 +180,9 @@ impl StmtVisitor for Interpreter {
     }
     
     fn visit_if_stmt(&mut self, stmt: Rc<IfStmt>) -> Result<Self::Output, LoxError> {
-        if !matches!(self.evaluate(&stmt.condition)?, Object::Nil | Object::Bool(false)) {
+        let taken = Self::is_truthy(&self.evaluate(&stmt.condition)?);
+        self.branch(|| Branch::Stmt(Stmt::If(Rc::clone(&stmt))), taken);
+        if taken {
             self.execute(&stmt.then_branch)
         } else if let Some(else_branch) = &stmt.else_branch {
             self.execute(else_branch)
@@ -160,13 +202,12 @@ impl StmtVisitor for Interpreter {
 
     fn visit_print_stmt(&mut self, stmt: Rc<PrintStmt>) -> Result<Self::Output, LoxError> {
         let value = self.evaluate(&stmt.expression)?;
-        println!("{}", value);
-        Ok(())
+        self.write_output(&format!("{value}\n"), Stream::Stdout).map_err(|e| e.at(&stmt.keyword))
     }
 
     fn visit_var_stmt(&mut self, stmt: Rc<VarStmt>) -> Result<Self::Output, LoxError> {
-        let value = if stmt.initializer.is_some() {
-            self.evaluate(stmt.initializer.as_ref().unwrap())?
+        let value = if let Some(initializer) = &stmt.initializer {
+            self.evaluate(initializer)?
         } else {
             Object::Nil
         };
@@ -180,11 +221,22 @@ impl Interpreter {
     pub fn new() -> Self {
         let globals = Rc::new(RefCell::new(Environment::new()));
         globals.borrow_mut().define("clock", &Object::Func(Rc::new(NativeClock)));
+        for native in natives::NUMBERS.iter().chain(natives::STRINGS).chain(natives::LISTS).chain(natives::MATH).chain(natives::RANDOM).chain(files::FILES).chain(natives::INPUT).chain(natives::OUTPUT) {
+            globals.borrow_mut().define(native.name, &Object::Func(Rc::new(*native)));
+        }
+        for (name, value) in natives::MATH_CONSTANTS {
+            globals.borrow_mut().define(name, &Object::Num(*value));
+        }
 
         Self {
             globals: Rc::clone(&globals), 
             environment: Rc::clone(&globals),
-            locals: HashMap::new()
+            locals: HashMap::new(),
+            hook: None,
+            random: Random::from_time(),
+            permissions: Permissions::default(),
+            input: None,
+            output: Box::new(io::stdout()),
         }
     }
 
@@ -197,9 +249,63 @@ impl Interpreter {
     }
 
     fn execute(&mut self, stmt: &Stmt) -> Result<(), LoxError> {
+        if let Some(hook) = &mut self.hook {
+            hook.statement(stmt, &self.environment)?;
+        }
         stmt.accept(self) 
     }
 
+    pub fn attach(&mut self, hook: Box<dyn ExecutionHook>) {
+        self.hook = Some(hook);
+    }
+
+    // The site is only built when a hook is there to see it.
+    fn branch(&mut self, site: impl FnOnce() -> Branch, taken: bool) {
+        if let Some(hook) = &mut self.hook {
+            hook.branch(&site(), taken);
+        }
+    }
+
+    pub fn detach(&mut self) -> Option<Box<dyn ExecutionHook>> {
+        self.hook.take()
+    }
+
+    // Scripts read from `input` instead of standard input, such as a buffer in tests.
+    pub fn set_input(&mut self, input: Box<dyn BufRead>) {
+        self.input = Some(input);
+    }
+
+    // Program output goes to `output` instead of stdout, such as a buffer in tests.
+    pub fn set_output(&mut self, output: Box<dyn Write>) {
+        self.output = output;
+    }
+
+    // Writes program output to one of the two streams, unless a hook takes it over. Stderr is
+    // never buffered, so `output` is flushed before anything is written there.
+    pub fn write_output(&mut self, text: &str, stream: Stream) -> Result<(), LoxError> {
+        if self.hook.as_mut().is_some_and(|hook| hook.print(text, stream)) {
+            return Ok(());
+        }
+        let result = match stream {
+            Stream::Stdout => self.output.write_all(text.as_bytes()),
+            Stream::Stderr => self.output.flush().and_then(|_| io::stderr().write_all(text.as_bytes())),
+        };
+        result.map_err(|e| LoxError::object_error(&format!("Cannot write output: {e}.")))
+    }
+
+    // Output may be buffered until this is called, which must happen before the program exits.
+    pub fn flush(&mut self) -> io::Result<()> {
+        self.output.flush()
+    }
+
+    // Standard input is only locked while it is read, so the REPL and the debugger can share it.
+    pub fn with_input<T>(&mut self, read: impl FnOnce(&mut dyn BufRead) -> T) -> T {
+        match &mut self.input {
+            Some(input) => read(input.as_mut()),
+            None => read(&mut io::stdin().lock()),
+        }
+    }
+
     pub fn execute_block(&mut self, statements: &[Stmt], environment: Environment) -> Result<(), LoxError> {
         let previous = Rc::clone(&self.environment);
         self.environment = Rc::new(RefCell::new(environment));
@@ -215,6 +321,11 @@ impl Interpreter {
         Ok(())
     }
 
+    // How many scopes out a variable resolved to, `None` for globals.
+    pub fn depth(&self, expr: &Expr) -> Option<usize> {
+        self.locals.get(expr).copied()
+    }
+
     fn look_up_variable(&mut self, name: &Token, expr: Expr) -> Result<Object, LoxError> {
         if let Some(distance) = self.locals.get(&expr) {
             self.environment.borrow().get_at(*distance, &name.lexeme)
@@ -223,20 +334,95 @@ impl Interpreter {
         }
     }
 
-    pub fn interpret(&mut self, stmts: Vec<Stmt>) {
+    pub fn interpret(&mut self, stmts: Vec<Stmt>) -> Result<(), LoxError> {
         for stmt in stmts {
-            match self.execute(&stmt) {
+            let result = self.execute(&stmt);
+            if result.is_err() {
+                // Output printed before the error goes out first, even to a buffer. If it
+                // cannot be written, the error is still worth reporting.
+                let _ = self.flush();
+            }
+            match result {
+                Err(LoxError::Runtime(token, message)) => {
+                    LoxError::report_runtime(&token, &message);
+                    return Err(LoxError::RuntimeError);
+                },
                 Err(LoxError::Break(line)) => {
                     LoxError::report(line, "", "'break' outside loop.");
-                    break;
+                    return Err(LoxError::RuntimeError);
                 },
                 Err(LoxError::Return(_, line)) => {
                     LoxError::report(line, "", "'return' outside a funcion.");
-                    break;
+                    return Err(LoxError::RuntimeError);
                 } 
-                Err(_) => break,
+                Err(LoxError::Abort) => return Err(LoxError::Abort),
+                Err(_) => return Err(LoxError::RuntimeError),
                 _ => {},
             }
         }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    // Output to a buffer the test keeps a handle on, as `Box<dyn Write>` cannot be read back.
+    #[derive(Clone, Default)]
+    struct Shared(Rc<RefCell<Vec<u8>>>);
+
+    impl Write for Shared {
+        fn write(&mut self, bytes: &[u8]) -> io::Result<usize> {
+            self.0.borrow_mut().write(bytes)
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    impl Shared {
+        fn text(&self) -> String {
+            String::from_utf8(self.0.borrow().clone()).unwrap()
+        }
+    }
+
+    fn run(interp: &mut Interpreter, source: &str) -> Result<(), LoxError> {
+        let (_, statements) = crate::compile(source.to_owned(), interp)?;
+        interp.interpret(statements)
+    }
+
+    #[test]
+    fn reads_the_input_it_is_given() {
+        let output = Shared::default();
+        let mut interp = Interpreter::new();
+        interp.set_input(Box::new(Cursor::new("Ada\nfirst\nsecond")));
+        interp.set_output(Box::new(output.clone()));
+        let source = "var name = input(\"name? \"); var rest = readAll(); print name; write(rest); print readLine();";
+        assert!(run(&mut interp, source).is_ok());
+        assert_eq!(output.text(), "name? Ada\nfirst\nsecondnil\n");
+    }
+
+    #[test]
+    fn writes_to_the_output_it_is_given() {
+        let output = Shared::default();
+        let mut interp = Interpreter::new();
+        interp.set_output(Box::new(io::BufWriter::new(output.clone())));
+        assert!(run(&mut interp, "print 1; write(\"two\"); print \" three\";").is_ok());
+        assert_eq!(output.text(), "");
+        interp.flush().unwrap();
+        assert_eq!(output.text(), "1\ntwo three\n");
+    }
+
+    // What was printed before a runtime error is flushed before the error is reported.
+    #[test]
+    fn flushes_the_output_before_a_runtime_error() {
+        let output = Shared::default();
+        let mut interp = Interpreter::new();
+        interp.set_output(Box::new(io::BufWriter::new(output.clone())));
+        assert!(matches!(run(&mut interp, "print \"before\"; print nil + 1; print \"after\";"), Err(LoxError::RuntimeError)));
+        assert_eq!(output.text(), "before\n");
     }
 }
@@ -0,0 +1,237 @@
+use std::rc::Rc;
+
+use crate::{expr::*, stmt::*, error::LoxError, object::Object, token::Token, parser::Trivia, interpreter::Interpreter, json::Json};
+
+// Bumped whenever a field is renamed or removed; new fields may appear without a bump.
+const SCHEMA_VERSION: usize = 1;
+
+// Serializes the token stream and the resolved AST. The schema is documented in README.md.
+pub struct JsonExporter<'a> {
+    tokens: &'a [Token],
+    trivia: &'a Trivia,
+    interpreter: &'a Interpreter,
+}
+
+impl<'a> ExprVisitor for JsonExporter<'a> {
+    type Output = Json;
+
+    fn visit_assign_expr(&mut self, expr: Rc<AssignExpr>) -> Result<Self::Output, LoxError> {
+        let assign = Expr::Assign(Rc::clone(&expr));
+        Ok(node("Assign", self.expr_span(&assign), vec![
+            ("name", token(&expr.name)),
+            ("value", expr.value.accept(self)?),
+            ("depth", self.interpreter.depth(&assign).into()),
+        ]))
+    }
+
+    fn visit_binary_expr(&mut self, expr: Rc<BinaryExpr>) -> Result<Self::Output, LoxError> {
+        Ok(node("Binary", self.expr_span(&Expr::Binary(Rc::clone(&expr))), vec![
+            ("left", expr.left.accept(self)?),
+            ("operator", token(&expr.operator)),
+            ("right", expr.right.accept(self)?),
+        ]))
+    }
+
+    fn visit_call_expr(&mut self, expr: Rc<CallExpr>) -> Result<Self::Output, LoxError> {
+        let arguments = expr.arguments.iter().map(|argument| argument.accept(self)).collect::<Result<Vec<_>, _>>()?;
+        Ok(node("Call", self.expr_span(&Expr::Call(Rc::clone(&expr))), vec![
+            ("callee", expr.callee.accept(self)?),
+            ("paren", token(&expr.paren)),
+            ("arguments", Json::Array(arguments)),
+        ]))
+    }
+
+    fn visit_grouping_expr(&mut self, expr: Rc<GroupingExpr>) -> Result<Self::Output, LoxError> {
+        Ok(node("Grouping", self.expr_span(&Expr::Grouping(Rc::clone(&expr))), vec![
+            ("expression", expr.expression.accept(self)?),
+        ]))
+    }
+
+    fn visit_interpolation_expr(&mut self, expr: Rc<InterpolationExpr>) -> Result<Self::Output, LoxError> {
+        let parts = expr.parts.iter().map(|part| part.accept(self)).collect::<Result<Vec<_>, _>>()?;
+        Ok(node("Interpolation", self.expr_span(&Expr::Interpolation(Rc::clone(&expr))), vec![
+            ("parts", Json::Array(parts)),
+        ]))
+    }
+
+    fn visit_literal_expr(&mut self, expr: Rc<LiteralExpr>) -> Result<Self::Output, LoxError> {
+        Ok(node("Literal", self.expr_span(&Expr::Literal(Rc::clone(&expr))), vec![
+            ("value", literal(&expr.value)),
+        ]))
+    }
+
+    fn visit_logical_expr(&mut self, expr: Rc<LogicalExpr>) -> Result<Self::Output, LoxError> {
+        Ok(node("Logical", self.expr_span(&Expr::Logical(Rc::clone(&expr))), vec![
+            ("left", expr.left.accept(self)?),
+            ("operator", token(&expr.operator)),
+            ("right", expr.right.accept(self)?),
+        ]))
+    }
+
+    fn visit_unary_expr(&mut self, expr: Rc<UnaryExpr>) -> Result<Self::Output, LoxError> {
+        Ok(node("Unary", self.expr_span(&Expr::Unary(Rc::clone(&expr))), vec![
+            ("operator", token(&expr.operator)),
+            ("right", expr.right.accept(self)?),
+        ]))
+    }
+
+    fn visit_variable_expr(&mut self, expr: Rc<VariableExpr>) -> Result<Self::Output, LoxError> {
+        let variable = Expr::Variable(Rc::clone(&expr));
+        Ok(node("Variable", self.expr_span(&variable), vec![
+            ("name", token(&expr.name)),
+            ("depth", self.interpreter.depth(&variable).into()),
+        ]))
+    }
+}
+
+impl<'a> StmtVisitor for JsonExporter<'a> {
+    type Output = Json;
+
+    fn visit_break_stmt(&mut self, stmt: Rc<BreakStmt>) -> Result<Self::Output, LoxError> {
+        Ok(node("Break", self.stmt_span(&Stmt::Break(stmt)), vec![]))
+    }
+
+    fn visit_block_stmt(&mut self, stmt: Rc<BlockStmt>) -> Result<Self::Output, LoxError> {
+        Ok(node("Block", self.stmt_span(&Stmt::Block(Rc::clone(&stmt))), vec![
+            ("statements", self.statements(&stmt.statements)?),
+        ]))
+    }
+
+    fn visit_expression_stmt(&mut self, stmt: Rc<ExpressionStmt>) -> Result<Self::Output, LoxError> {
+        Ok(node("Expression", self.stmt_span(&Stmt::Expression(Rc::clone(&stmt))), vec![
+            ("expression", stmt.expression.accept(self)?),
+        ]))
+    }
+
+    fn visit_function_stmt(&mut self, stmt: Rc<FunctionStmt>) -> Result<Self::Output, LoxError> {
+        Ok(node("Function", self.stmt_span(&Stmt::Function(Rc::clone(&stmt))), vec![
+            ("name", token(&stmt.name)),
+            ("parameters", Json::Array(stmt.parameters.iter().map(token).collect())),
+            ("body", self.statements(&stmt.body)?),
+        ]))
+    }
+
+    fn visit_if_stmt(&mut self, stmt: Rc<IfStmt>) -> Result<Self::Output, LoxError> {
+        let else_branch = match &stmt.else_branch {
+            Some(else_branch) => else_branch.accept(self)?,
+            None => Json::Null,
+        };
+        Ok(node("If", self.stmt_span(&Stmt::If(Rc::clone(&stmt))), vec![
+            ("condition", stmt.condition.accept(self)?),
+            ("then_branch", stmt.then_branch.accept(self)?),
+            ("else_branch", else_branch),
+        ]))
+    }
+
+    fn visit_print_stmt(&mut self, stmt: Rc<PrintStmt>) -> Result<Self::Output, LoxError> {
+        Ok(node("Print", self.stmt_span(&Stmt::Print(Rc::clone(&stmt))), vec![
+            ("keyword", token(&stmt.keyword)),
+            ("expression", stmt.expression.accept(self)?),
+        ]))
+    }
+
+    fn visit_return_stmt(&mut self, stmt: Rc<ReturnStmt>) -> Result<Self::Output, LoxError> {
+        Ok(node("Return", self.stmt_span(&Stmt::Return(Rc::clone(&stmt))), vec![
+            ("keyword", token(&stmt.keyword)),
+            ("value", stmt.value.accept(self)?),
+        ]))
+    }
+
+    fn visit_var_stmt(&mut self, stmt: Rc<VarStmt>) -> Result<Self::Output, LoxError> {
+        let initializer = match &stmt.initializer {
+            Some(initializer) => initializer.accept(self)?,
+            None => Json::Null,
+        };
+        Ok(node("Var", self.stmt_span(&Stmt::Var(Rc::clone(&stmt))), vec![
+            ("name", token(&stmt.name)),
+            ("initializer", initializer),
+        ]))
+    }
+
+    fn visit_while_stmt(&mut self, stmt: Rc<WhileStmt>) -> Result<Self::Output, LoxError> {
+        Ok(node("While", self.stmt_span(&Stmt::While(Rc::clone(&stmt))), vec![
+            ("condition", stmt.condition.accept(self)?),
+            ("body", stmt.body.accept(self)?),
+        ]))
+    }
+}
+
+impl<'a> JsonExporter<'a> {
+    pub fn new(tokens: &'a [Token], trivia: &'a Trivia, interpreter: &'a Interpreter) -> Self {
+        Self { tokens, trivia, interpreter }
+    }
+
+    pub fn export(&mut self, statements: &[Stmt]) -> Result<Json, LoxError> {
+        Ok(Json::object([
+            ("version", SCHEMA_VERSION.into()),
+            ("tokens", Json::Array(self.tokens.iter().map(token).collect())),
+            ("statements", self.statements(statements)?),
+        ]))
+    }
+
+    fn statements(&mut self, statements: &[Stmt]) -> Result<Json, LoxError> {
+        Ok(Json::Array(statements.iter().map(|stmt| stmt.accept(self)).collect::<Result<_, _>>()?))
+    }
+
+    fn stmt_span(&self, stmt: &Stmt) -> Json {
+        self.span(self.trivia.spans.get(stmt))
+    }
+
+    fn expr_span(&self, expr: &Expr) -> Json {
+        self.span(self.trivia.expr_spans.get(expr))
+    }
+
+    // Nodes made up by desugaring, like the `true` of `for (;;)`, have no span.
+    fn span(&self, range: Option<&(usize, usize)>) -> Json {
+        match range {
+            Some((first, last)) => Json::object([
+                ("start", start(&self.tokens[*first])),
+                ("end", end(&self.tokens[*last])),
+            ]),
+            None => Json::Null,
+        }
+    }
+}
+
+fn node(kind: &str, span: Json, fields: Vec<(&str, Json)>) -> Json {
+    let mut node = vec![("kind".to_owned(), kind.into()), ("span".to_owned(), span)];
+    node.extend(fields.into_iter().map(|(key, value)| (key.to_owned(), value)));
+    Json::Object(node)
+}
+
+fn token(token: &Token) -> Json {
+    Json::object([
+        ("type", format!("{:?}", token.ttype).into()),
+        ("lexeme", token.lexeme.as_str().into()),
+        ("literal", literal(&token.literal)),
+        ("span", Json::object([("start", start(token)), ("end", end(token))])),
+    ])
+}
+
+fn literal(value: &Option<Object>) -> Json {
+    match value {
+        Some(Object::Int(x)) => Json::Int(*x),
+        Some(Object::Num(x)) => Json::Num(*x),
+        Some(x @ (Object::Big(_) | Object::Rat(_) | Object::List(_))) => x.to_string().into(),
+        Some(Object::Str(x)) => x.as_str().into(),
+        Some(Object::Bool(x)) => (*x).into(),
+        Some(Object::Func(x)) => x.to_string().into(),
+        Some(Object::Nil) | None => Json::Null,
+    }
+}
+
+fn position(line: usize, column: usize) -> Json {
+    Json::object([("line", line.into()), ("column", column.into())])
+}
+
+// `Token::line` is the line a token ends on, which differs from where it starts for multi-line strings.
+fn start(token: &Token) -> Json {
+    position(token.line - token.lexeme.matches('\n').count(), token.column)
+}
+
+fn end(token: &Token) -> Json {
+    match token.lexeme.rsplit_once('\n') {
+        Some((_, last)) => position(token.line, last.chars().count()),
+        None => position(token.line, token.column + token.lexeme.chars().count().max(1) - 1),
+    }
+}
@@ -27,7 +27,11 @@ impl LoxCallable for LoxFunction {
     }
 
     fn name(&self) -> &str {
-        ""
+        &self.declaration.name.lexeme
+    }
+
+    fn line(&self) -> Option<usize> {
+        Some(self.declaration.name.line)
     }
 }
 
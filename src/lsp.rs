@@ -0,0 +1,361 @@
+use std::collections::HashMap;
+use std::io::{self, BufRead, Write};
+
+use crate::{stmt::*, error::{LoxError, Diagnostic}, token::Token, scanner::Scanner, parser::{Parser, Trivia},
+    interpreter::Interpreter, resolver::{Resolver, Symbol, SymbolKind}, json::Json};
+
+const PARSE_ERROR: i64 = -32700;
+const INVALID_REQUEST: i64 = -32600;
+const METHOD_NOT_FOUND: i64 = -32601;
+const INVALID_PARAMS: i64 = -32602;
+
+// LSP `SymbolKind.Function`.
+const FUNCTION_SYMBOL: usize = 12;
+
+// A Language Server Protocol server. Documents are synchronized in full on every change
+// and analysed again from scratch, which is cheap at the size of Lox programs.
+pub struct LanguageServer {
+    documents: HashMap<String, Document>,
+    outgoing: Vec<Json>,    // notifications to send once the current message is handled
+    shutdown: bool,
+}
+
+struct Document {
+    lines: Vec<String>,
+    symbols: Vec<Symbol>,
+    functions: Vec<Function>,
+    diagnostics: Vec<Diagnostic>,
+}
+
+// A function declaration for the document outline, with the tokens its range starts and ends at.
+struct Function {
+    name: Token,
+    detail: String,
+    first: Token,
+    last: Token,
+    children: Vec<Function>,
+}
+
+struct ResponseError(i64, String);
+
+impl LanguageServer {
+    pub fn new() -> Self {
+        Self { documents: HashMap::new(), outgoing: Vec::new(), shutdown: false }
+    }
+
+    // Returns the exit code: 0 if the client asked to shut down before exiting.
+    pub fn serve(&mut self, mut reader: impl BufRead, mut writer: impl Write) -> io::Result<i32> {
+        while let Some(body) = read_message(&mut reader)? {
+            let message = match Json::parse(&body) {
+                Ok(message) => message,
+                Err(e) => {
+                    write_message(&mut writer, &response(&Json::Null, Err(ResponseError(PARSE_ERROR, e))))?;
+                    continue;
+                },
+            };
+
+            let method = message.get("method").as_str().unwrap_or_default();
+            if method == "exit" {
+                return Ok(if self.shutdown { 0 } else { 1 });
+            }
+
+            let result = self.handle(method, message.get("params"));
+            let id = message.get("id");
+            if *id != Json::Null {
+                write_message(&mut writer, &response(id, result))?;
+            }
+            for notification in std::mem::take(&mut self.outgoing) {
+                write_message(&mut writer, &notification)?;
+            }
+        }
+        Ok(1)
+    }
+
+    fn handle(&mut self, method: &str, params: &Json) -> Result<Json, ResponseError> {
+        if self.shutdown {
+            return Err(ResponseError(INVALID_REQUEST, "Server is shutting down.".to_owned()));
+        }
+
+        match method {
+            "initialize" => Ok(Json::object([
+                ("capabilities", Json::object([
+                    ("textDocumentSync", Json::Num(1.0)),     // full
+                    ("hoverProvider", true.into()),
+                    ("definitionProvider", true.into()),
+                    ("referencesProvider", true.into()),
+                    ("documentSymbolProvider", true.into()),
+                ])),
+                ("serverInfo", Json::object([("name", "rlox".into()), ("version", env!("CARGO_PKG_VERSION").into())])),
+            ])),
+            "shutdown" => {
+                self.shutdown = true;
+                Ok(Json::Null)
+            },
+            "textDocument/didOpen" => {
+                let document = params.get("textDocument");
+                self.update(uri(params)?, document.get("text").as_str().unwrap_or_default());
+                Ok(Json::Null)
+            },
+            "textDocument/didChange" => {
+                let text = params.get("contentChanges").as_array().last().and_then(|change| change.get("text").as_str());
+                self.update(uri(params)?, text.unwrap_or_default());
+                Ok(Json::Null)
+            },
+            "textDocument/didClose" => {
+                let uri = uri(params)?;
+                self.documents.remove(uri);
+                self.outgoing.push(publish_diagnostics(uri, Vec::new()));
+                Ok(Json::Null)
+            },
+            "textDocument/definition" => {
+                let uri = uri(params)?;
+                let document = self.document(uri)?;
+                Ok(match document.symbol_at(params.get("position")) {
+                    Some((symbol, _)) => document.location(uri, &symbol.name),
+                    None => Json::Null,
+                })
+            },
+            "textDocument/references" => {
+                let uri = uri(params)?;
+                let document = self.document(uri)?;
+                let include_declaration = params.get("context").get("includeDeclaration").as_bool().unwrap_or(true);
+                Ok(match document.symbol_at(params.get("position")) {
+                    Some((symbol, _)) => {
+                        let declaration = include_declaration.then_some(&symbol.name);
+                        Json::Array(declaration.into_iter().chain(&symbol.references).map(|token| document.location(uri, token)).collect())
+                    },
+                    None => Json::Null,
+                })
+            },
+            "textDocument/hover" => {
+                let document = self.document(uri(params)?)?;
+                Ok(match document.symbol_at(params.get("position")) {
+                    Some((symbol, token)) => Json::object([
+                        ("contents", Json::object([("kind", "markdown".into()), ("value", hover(symbol).into())])),
+                        ("range", document.token_range(token)),
+                    ]),
+                    None => Json::Null,
+                })
+            },
+            "textDocument/documentSymbol" => {
+                let document = self.document(uri(params)?)?;
+                Ok(Json::Array(document.functions.iter().map(|function| document.outline(function)).collect()))
+            },
+            _ => Err(ResponseError(METHOD_NOT_FOUND, format!("Unhandled method '{method}'."))),
+        }
+    }
+
+    fn update(&mut self, uri: &str, text: &str) {
+        let document = Document::analyze(text);
+        let diagnostics = document.diagnostics.iter().map(|diagnostic| document.diagnostic(diagnostic)).collect();
+        self.outgoing.push(publish_diagnostics(uri, diagnostics));
+        self.documents.insert(uri.to_owned(), document);
+    }
+
+    fn document(&self, uri: &str) -> Result<&Document, ResponseError> {
+        self.documents.get(uri).ok_or_else(|| ResponseError(INVALID_PARAMS, format!("Unknown document '{uri}'.")))
+    }
+}
+
+impl Document {
+    // Runs the front end with its errors collected, keeping whatever it got through.
+    fn analyze(text: &str) -> Self {
+        let ((symbols, functions), diagnostics) = LoxError::capture(|| {
+            let mut scanner = Scanner::new(text.to_owned());
+            let Ok(tokens) = scanner.scan_tokens() else {
+                return (Vec::new(), Vec::new());
+            };
+
+            let mut parser = Parser::new(tokens.to_owned());
+            let Ok(statements) = parser.parse() else {
+                return (Vec::new(), Vec::new());
+            };
+            let functions = functions(&statements, parser.tokens(), parser.trivia());
+
+            let mut interp = Interpreter::new();
+            let mut resolver = Resolver::new(&mut interp);
+            let _ = resolver.resolve(&statements);
+            (resolver.symbols(), functions)
+        });
+
+        let lines = text.split('\n').map(|line| line.trim_end_matches('\r').to_owned()).collect();
+        Self { lines, symbols, functions, diagnostics }
+    }
+
+    fn symbol_at(&self, position: &Json) -> Option<(&Symbol, &Token)> {
+        let line = position.get("line").as_usize()? + 1;
+        let column = self.column(line, position.get("character").as_usize()?);
+        self.symbols.iter().find_map(|symbol| {
+            std::iter::once(&symbol.name).chain(&symbol.references)
+                .find(|token| token.line == line && (token.column..=token.column + token.lexeme.chars().count()).contains(&column))
+                .map(|token| (symbol, token))
+        })
+    }
+
+    fn diagnostic(&self, diagnostic: &Diagnostic) -> Json {
+        let range = match (&diagnostic.token, diagnostic.column) {
+            (Some(token), _) => self.token_range(token),
+            (None, Some(column)) => self.range((diagnostic.line, column), (diagnostic.line, column + 1)),
+            (None, None) => self.range((diagnostic.line, 1), (diagnostic.line, self.line(diagnostic.line).chars().count() + 1)),
+        };
+        Json::object([
+            ("range", range),
+            ("severity", Json::Num(1.0)),     // error
+            ("source", "rlox".into()),
+            ("message", diagnostic.message.as_str().into()),
+        ])
+    }
+
+    fn outline(&self, function: &Function) -> Json {
+        Json::object([
+            ("name", function.name.lexeme.as_str().into()),
+            ("detail", function.detail.as_str().into()),
+            ("kind", FUNCTION_SYMBOL.into()),
+            ("range", self.range(self.start(&function.first), self.end(&function.last))),
+            ("selectionRange", self.token_range(&function.name)),
+            ("children", Json::Array(function.children.iter().map(|child| self.outline(child)).collect())),
+        ])
+    }
+
+    fn location(&self, uri: &str, token: &Token) -> Json {
+        Json::object([("uri", uri.into()), ("range", self.token_range(token))])
+    }
+
+    fn token_range(&self, token: &Token) -> Json {
+        self.range(self.start(token), self.end(token))
+    }
+
+    // `Token::line` is the line a token ends on, which differs from where it starts for multi-line strings.
+    fn start(&self, token: &Token) -> (usize, usize) {
+        (token.line - token.lexeme.matches('\n').count(), token.column)
+    }
+
+    fn end(&self, token: &Token) -> (usize, usize) {
+        match token.lexeme.rsplit_once('\n') {
+            Some((_, last)) => (token.line, last.chars().count() + 1),
+            None => (token.line, token.column + token.lexeme.chars().count()),
+        }
+    }
+
+    // Lines and columns here are 1-based and count characters; LSP counts both from 0 and columns in UTF-16 units.
+    fn range(&self, start: (usize, usize), end: (usize, usize)) -> Json {
+        Json::object([("start", self.position(start)), ("end", self.position(end))])
+    }
+
+    fn position(&self, (line, column): (usize, usize)) -> Json {
+        let character: usize = self.line(line).chars().take(column - 1).map(char::len_utf16).sum();
+        Json::object([("line", (line - 1).into()), ("character", character.into())])
+    }
+
+    fn column(&self, line: usize, character: usize) -> usize {
+        let mut units = 0;
+        let mut column = 1;
+        for ch in self.line(line).chars() {
+            if units >= character {
+                break;
+            }
+            units += ch.len_utf16();
+            column += 1;
+        }
+        column
+    }
+
+    fn line(&self, line: usize) -> &str {
+        self.lines.get(line.wrapping_sub(1)).map_or("", String::as_str)
+    }
+}
+
+fn functions(statements: &[Stmt], tokens: &[Token], trivia: &Trivia) -> Vec<Function> {
+    let mut functions = Vec::new();
+    for stmt in statements {
+        match stmt {
+            Stmt::Function(function) => {
+                let Some(&(first, last)) = trivia.spans.get(stmt) else { continue };
+                let parameters: Vec<&str> = function.parameters.iter().map(|param| param.lexeme.as_str()).collect();
+                functions.push(Function {
+                    name: function.name.clone(),
+                    detail: format!("fun {}({})", function.name.lexeme, parameters.join(", ")),
+                    first: tokens[first].clone(),
+                    last: tokens[last].clone(),
+                    children: self::functions(&function.body, tokens, trivia),
+                });
+            },
+            Stmt::Block(block) => functions.extend(self::functions(&block.statements, tokens, trivia)),
+            Stmt::If(stmt) => {
+                functions.extend(self::functions(std::slice::from_ref(&stmt.then_branch), tokens, trivia));
+                if let Some(else_branch) = &stmt.else_branch {
+                    functions.extend(self::functions(std::slice::from_ref(else_branch), tokens, trivia));
+                }
+            },
+            Stmt::While(stmt) => functions.extend(self::functions(std::slice::from_ref(&stmt.body), tokens, trivia)),
+            _ => {},
+        }
+    }
+    functions
+}
+
+fn hover(symbol: &Symbol) -> String {
+    let kind = match (symbol.kind, symbol.global) {
+        (SymbolKind::Function, true) => "global function",
+        (SymbolKind::Function, false) => "local function",
+        (SymbolKind::Variable, true) => "global variable",
+        (SymbolKind::Variable, false) => "local variable",
+        (SymbolKind::Parameter, _) => "parameter",
+    };
+    format!("```lox\n{}\n```\n{kind}, declared on line {}", symbol.detail, symbol.name.line)
+}
+
+fn uri(params: &Json) -> Result<&str, ResponseError> {
+    params.get("textDocument").get("uri").as_str()
+        .ok_or_else(|| ResponseError(INVALID_PARAMS, "Missing textDocument.uri.".to_owned()))
+}
+
+fn publish_diagnostics(uri: &str, diagnostics: Vec<Json>) -> Json {
+    Json::object([
+        ("jsonrpc", "2.0".into()),
+        ("method", "textDocument/publishDiagnostics".into()),
+        ("params", Json::object([("uri", uri.into()), ("diagnostics", Json::Array(diagnostics))])),
+    ])
+}
+
+fn response(id: &Json, result: Result<Json, ResponseError>) -> Json {
+    match result {
+        Ok(result) => Json::object([("jsonrpc", "2.0".into()), ("id", id.clone()), ("result", result)]),
+        Err(ResponseError(code, message)) => Json::object([
+            ("jsonrpc", "2.0".into()),
+            ("id", id.clone()),
+            ("error", Json::object([("code", Json::Num(code as f64)), ("message", message.into())])),
+        ]),
+    }
+}
+
+// Messages are framed by a `Content-Length` header, as in both LSP and the Debug Adapter Protocol.
+pub fn read_message(reader: &mut impl BufRead) -> io::Result<Option<String>> {
+    let mut length = None;
+    loop {
+        let mut header = String::new();
+        if reader.read_line(&mut header)? == 0 {
+            return Ok(None);
+        }
+        let header = header.trim_end();
+        if header.is_empty() {
+            break;
+        }
+        if let Some((name, value)) = header.split_once(':') {
+            if name.eq_ignore_ascii_case("Content-Length") {
+                length = value.trim().parse().ok();
+            }
+        }
+    }
+
+    let length = length.ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "Missing Content-Length header."))?;
+    let mut body = vec![0; length];
+    reader.read_exact(&mut body)?;
+    String::from_utf8(body).map(Some).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+}
+
+pub fn write_message(writer: &mut impl Write, message: &Json) -> io::Result<()> {
+    let body = message.to_string();
+    write!(writer, "Content-Length: {}\r\n\r\n{body}", body.len())?;
+    writer.flush()
+}
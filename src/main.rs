@@ -10,82 +10,366 @@ mod environment;
 mod callable;
 mod loxfunction;
 mod resolver;
+mod astprinter;
+mod json;
+mod jsonexport;
+mod formatter;
+mod testrunner;
+mod lsp;
+mod hook;
+mod debugger;
+mod dap;
+mod profiler;
+mod coverage;
+mod tracer;
+mod bigint;
+mod rational;
+mod natives;
+mod unicode;
+mod random;
+mod files;
 
 use interpreter::Interpreter;
 use parser::Parser;
 use resolver::Resolver;
 use scanner::Scanner;
+use astprinter::{AstPrinter, AstFormat};
 use error::LoxError;
+use formatter::Formatter;
+use jsonexport::JsonExporter;
+use testrunner::TestRunner;
+use lsp::LanguageServer;
+use debugger::{Debugger, Console};
+use stmt::Stmt;
+use hook::ExecutionHook;
+use profiler::Profiler;
+use coverage::Coverage;
+use tracer::Tracer;
+use files::Permissions;
 
-use std::{io::{self, BufRead, Write}, env, fs};
+use std::{io::{self, IsTerminal, Write}, env, fs};
 
 fn main() {
     let args: Vec<String> = env::args().collect();
+    let (permissions, args) = permissions(&args[1..]);
+    if let [command, ..] = args {
+        if !takes_permissions(command) && permissions.any() {
+            eprintln!("'{command}' does not take --allow-read or --allow-write.");
+            std::process::exit(64);
+        }
+    }
+
+    match args {
+        [] => run_prompt(permissions).expect("Cannot run prompt."),
+        [command, dir] if command == "test" => run_tests(dir).expect("Cannot run tests"),
+        [command] if command == "lsp" => run_lsp().expect("Cannot run language server"),
+        [command, path] if command == "debug" => debug_file(path, permissions).expect("Cannot debug file"),
+        [command] if command == "dap" => dap::serve(permissions).expect("Cannot run debug adapter"),
+        [command, rest @ ..] if command == "fmt" => run_fmt(rest).expect("Cannot format files"),
+        [flag, path] if flag.starts_with("--dump-ast") => {
+            let format = match flag.as_str() {
+                "--dump-ast" | "--dump-ast=sexpr" => AstFormat::SExpr,
+                "--dump-ast=tree" => AstFormat::Tree,
+                _ => usage(),
+            };
+            dump_ast(path, format).expect("Cannot dump file");
+        },
+        [flag, path] if flag == "--json" => export_json(path).expect("Cannot export file"),
+        [flag, path] if flag.starts_with("--profile") => {
+            let output = flag.strip_prefix("--profile=").unwrap_or("profile.folded").to_owned();
+            run_with_hook(path, permissions, |_, parser, _| Box::new(Profiler::new(parser.statement_lines(), output.into())))
+                .expect("Cannot profile file");
+        },
+        [flag, path] if flag.starts_with("--coverage") => {
+            let output = flag.strip_prefix("--coverage=").unwrap_or("lcov.info").to_owned();
+            let script = fs::canonicalize(path).unwrap_or_else(|_| path.into());
+            run_with_hook(path, permissions, |source, parser, statements| {
+                Box::new(Coverage::new(script, source, output.into(), statements, parser.statement_lines()))
+            }).expect("Cannot measure coverage");
+        },
+        [flag, rest @ ..] if flag.starts_with("--trace") => {
+            let only = match flag.strip_prefix("--trace=") {
+                Some(names) => names.split(',').map(str::to_owned).collect(),
+                None if flag == "--trace" => Vec::new(),
+                None => usage(),
+            };
+            let (output, path) = match rest {
+                [path] => (None, path),
+                [o, output, path] if o == "-o" => (Some(output), path),
+                _ => usage(),
+            };
+            let out: Box<dyn Write> = match output {
+                Some(output) => Box::new(io::BufWriter::new(fs::File::create(output).expect("Cannot create trace file"))),
+                None => Box::new(io::stdout()),
+            };
+            run_with_hook(path, permissions, |source, parser, _| Box::new(Tracer::new(parser.statement_lines(), source, only, out)))
+                .expect("Cannot trace file");
+        },
+        [path] => run_file(path, permissions).expect("Cannot run file"),
+        _ => usage(),
+    }
+}
 
-    match args.len() {
-        1 => run_prompt().expect("Cannot run prompt."),
-        2 => run_file(&args[1]).expect("Cannot run file"),
-        _ => {
-            println!("Usage: rlox [script]");
+// `--allow-read=dir` and `--allow-write=dir` come first, as often as needed, and let scripts
+// use files beneath `dir`.
+fn permissions(args: &[String]) -> (Permissions, &[String]) {
+    let mut permissions = Permissions::default();
+    let mut rest = args;
+    while let [flag, tail @ ..] = rest {
+        let (dir, granted) = match (flag.strip_prefix("--allow-read="), flag.strip_prefix("--allow-write=")) {
+            (Some(dir), _) => (dir, permissions.allow_read(dir)),
+            (_, Some(dir)) => (dir, permissions.allow_write(dir)),
+            _ => break,
+        };
+        if let Err(e) = granted {
+            eprintln!("Cannot allow access to '{dir}': {e}.");
             std::process::exit(64);
         }
+        rest = tail;
     }
+    (permissions, rest)
+}
+
+// Whether a command runs scripts that may be given permissions. `test` runs each script as it
+// would run alone, with none; the others run no script at all.
+fn takes_permissions(command: &str) -> bool {
+    !matches!(command.split('=').next(), Some("test" | "lsp" | "fmt" | "--json" | "--dump-ast"))
+}
+
+fn usage() -> ! {
+    println!("Usage: rlox [--allow-read=dir] [--allow-write=dir] [script]");
+    println!("       rlox test [dir]");
+    println!("       rlox lsp");
+    println!("       rlox [--allow-read=dir] [--allow-write=dir] debug [script]");
+    println!("       rlox [--allow-read=dir] [--allow-write=dir] dap");
+    println!("       rlox --profile[=out.folded] [script]");
+    println!("       rlox --coverage[=lcov.info] [script]");
+    println!("       rlox --trace[=function,...] [-o file] [script]");
+    println!("       rlox fmt [--check] [--indent n] [script...]");
+    println!("       rlox --dump-ast[=sexpr|tree] [script]");
+    println!("       rlox --json [script]");
+    std::process::exit(64);
 }
 
-fn run_file(path: &str) -> io::Result<()> {
+fn run_file(path: &str, permissions: Permissions) -> io::Result<()> {
     let bytes = fs::read_to_string(path)?;
     let mut interp = Interpreter::new();
-    if run(bytes, &mut interp).is_err() {
+    interp.permissions = permissions;
+    // Nobody watches output that does not go to a terminal line by line, so it is buffered.
+    if !io::stdout().is_terminal() {
+        interp.set_output(Box::new(io::BufWriter::new(io::stdout())));
+    }
+    let result = run(bytes, &mut interp);
+    interp.flush()?;
+    match result {
+        Err(LoxError::RuntimeError) => std::process::exit(70),
+        Err(_) => std::process::exit(65),
+        Ok(_) => Ok(()),
+    }
+}
+
+// Runs a script with a tool watching it, and lets the tool report once the script is done.
+fn run_with_hook(path: &str, permissions: Permissions, hook: impl FnOnce(&str, &Parser, &[Stmt]) -> Box<dyn ExecutionHook>) -> io::Result<()> {
+    let source = fs::read_to_string(path)?;
+    let mut interp = Interpreter::new();
+    interp.permissions = permissions;
+    let (parser, statements) = match compile(source.clone(), &mut interp) {
+        Ok(compiled) => compiled,
+        Err(_) => std::process::exit(65),
+    };
+
+    interp.attach(hook(&source, &parser, &statements));
+    let result = interp.interpret(statements);
+    interp.flush()?;
+    if let Some(mut hook) = interp.detach() {
+        hook.finish()?;
+    }
+    match result {
+        Err(_) => std::process::exit(70),
+        Ok(_) => Ok(()),
+    }
+}
+
+fn run_tests(dir: &str) -> io::Result<()> {
+    let mut runner = TestRunner::new(env::current_exe()?);
+    runner.run_dir(dir.as_ref())?;
+    runner.summary();
+    if !runner.success() {
+        std::process::exit(1);
+    }
+    Ok(())
+}
+
+fn debug_file(path: &str, permissions: Permissions) -> io::Result<()> {
+    let source = fs::read_to_string(path)?;
+    let mut interp = Interpreter::new();
+    interp.permissions = permissions;
+    let (parser, statements) = match compile(source.clone(), &mut interp) {
+        Ok(compiled) => compiled,
+        Err(_) => std::process::exit(65),
+    };
+
+    let console = Console::new(&source);
+    let debugger = Debugger::new(parser.statement_lines(), &interp.globals, Box::new(console));
+    interp.attach(Box::new(debugger));
+    let result = interp.interpret(statements);
+    interp.flush()?;
+    match result {
+        Err(LoxError::Abort) => Ok(()),
+        Err(_) => std::process::exit(70),
+        Ok(_) => {
+            println!("Program finished.");
+            Ok(())
+        },
+    }
+}
+
+fn run_lsp() -> io::Result<()> {
+    let code = LanguageServer::new().serve(io::stdin().lock(), io::stdout().lock())?;
+    std::process::exit(code);
+}
+
+fn run_fmt(args: &[String]) -> io::Result<()> {
+    let mut check = false;
+    let mut indent = 4;
+    let mut paths = Vec::new();
+
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--check" => check = true,
+            "--indent" => indent = args.next().and_then(|n| n.parse().ok()).unwrap_or_else(|| usage()),
+            path => paths.push(path),
+        }
+    }
+
+    let mut unformatted = false;
+    for path in paths {
+        let source = fs::read_to_string(path)?;
+        let formatted = match format(&source, indent) {
+            Ok(formatted) => formatted,
+            Err(_) => std::process::exit(65),
+        };
+
+        if formatted != source {
+            if check {
+                println!("{path} is not formatted.");
+                unformatted = true;
+            } else {
+                fs::write(path, formatted)?;
+            }
+        }
+    }
+
+    if unformatted {
+        std::process::exit(1);
+    }
+    Ok(())
+}
+
+fn dump_ast(path: &str, format: AstFormat) -> io::Result<()> {
+    let mut scanner = Scanner::new(fs::read_to_string(path)?);
+    let tokens = match scanner.scan_tokens() {
+        Ok(tokens) => tokens,
+        Err(_) => std::process::exit(65),
+    };
+
+    let mut parser = Parser::new(tokens.to_owned());
+    match parser.parse() {
+        Ok(statements) if parser.success() => {
+            print!("{}", AstPrinter::new(format).print(&statements).expect("Cannot print AST"));
+            Ok(())
+        },
+        _ => std::process::exit(65),
+    }
+}
+
+fn export_json(path: &str) -> io::Result<()> {
+    let mut scanner = Scanner::new(fs::read_to_string(path)?);
+    let tokens = match scanner.scan_tokens() {
+        Ok(tokens) => tokens,
+        Err(_) => std::process::exit(65),
+    };
+
+    let mut parser = Parser::new(tokens.to_owned());
+    let statements = match parser.parse() {
+        Ok(statements) if parser.success() => statements,
+        _ => std::process::exit(65),
+    };
+
+    let mut interp = Interpreter::new();
+    let mut resolver = Resolver::new(&mut interp);
+    if resolver.resolve(&statements).is_err() || !resolver.success() {
         std::process::exit(65);
     }
-    Ok(())     
+
+    let json = JsonExporter::new(parser.tokens(), parser.trivia(), &interp).export(&statements).expect("Cannot export AST");
+    println!("{json}");
+    Ok(())
 }
 
-fn run_prompt() -> io::Result<()>{
-    let mut reader = io::stdin().lock().lines();
+// Standard input is locked a line at a time, as scripts may read it too.
+fn run_prompt(permissions: Permissions) -> io::Result<()>{
     let mut interp = Interpreter::new();
+    interp.permissions = permissions;
     loop {
         print!("> ");
         io::stdout().flush()?; 
         
-        match reader.next(){
-            None => break,
-            Some(line) => match line {
-                Err(e) => return Err(e),
-                Ok(line) => if run(line, &mut interp).is_err() {},
-            }
+        let mut line = String::new();
+        match io::stdin().read_line(&mut line)? {
+            0 => break,
+            _ => if run(line, &mut interp).is_err() {},
         } 
+        interp.flush()?;
     }  
     Ok(()) 
 }
 
 
 fn run(source: String, interp: &mut Interpreter) -> Result<(), LoxError> {
+    let (_, statements) = compile(source, interp)?;
+    interp.interpret(statements)
+}
+
+// Scans, parses and resolves a script. The parser is kept for the side tables tools need.
+fn compile(source: String, interp: &mut Interpreter) -> Result<(Parser, Vec<Stmt>), LoxError> {
     let mut scanner = Scanner::new(source);
     let tokens = scanner.scan_tokens()?;
 
     let mut parser = Parser::new(tokens.to_owned());
     
     
-    let statements = match parser.parse() {
-        Ok(expr) => expr,
-        Err(_) => return Ok(()),
-    };
+    let statements = parser.parse()?;
     
     if !parser.success() {
-        return Ok(());
+        return Err(LoxError::ParseError);
     }
 
     let mut resolver = Resolver::new(interp);
     resolver.resolve(&statements)?;
     if !resolver.success() {
-        return Ok(());
+        return Err(LoxError::ParseError);
     }
     
-    interp.interpret(statements);
-
-    // let printer = AstPrinter {};
-    // println!("{}", printer.print(&expression)?);
-    Ok(())
+    Ok((parser, statements))
 }
 
+// Formats with `\n` line breaks, and puts `\r\n` back afterwards for sources written with it.
+// Strings spanning lines are scanned with `\n` as well, so they keep the line breaks they had.
+fn format(source: &str, indent: usize) -> Result<String, LoxError> {
+    let mut scanner = Scanner::new(source.replace("\r\n", "\n"));
+    let tokens = scanner.scan_tokens()?;
+
+    let mut parser = Parser::new(tokens.to_owned());
+    let statements = parser.parse()?;
+    if !parser.success() {
+        return Err(LoxError::ParseError);
+    }
+
+    let formatted = Formatter::new(indent, parser.tokens(), scanner.comments(), parser.trivia()).format(&statements)?;
+    match source.contains("\r\n") {
+        true => Ok(formatted.replace('\n', "\r\n")),
+        false => Ok(formatted),
+    }
+}
@@ -0,0 +1,589 @@
+use std::cell::RefCell;
+
+use crate::{callable::NativeFunction, interpreter::Interpreter, object::Object, error::LoxError, rational::Rational, bigint::BigInt, random::Random, hook::Stream};
+
+// Conversions between the numeric types. Rationals are never made implicitly,
+// so exact arithmetic starts with `exact` or `rational`.
+pub const NUMBERS: &[NativeFunction] = &[
+    NativeFunction { name: "exact", arity: 1, function: exact },
+    NativeFunction { name: "rational", arity: 2, function: rational },
+    NativeFunction { name: "float", arity: 1, function: float },
+    NativeFunction { name: "int", arity: 1, function: int },
+    NativeFunction { name: "fixed", arity: 2, function: fixed },
+];
+
+// A number as a rational: floats by their exact binary value, and strings such as
+// "0.10" or "1/3" by their decimal value, which is usually what is meant.
+fn exact(_interpreter: &mut Interpreter, arguments: &[Object]) -> Result<Object, LoxError> {
+    let value = match &arguments[0] {
+        Object::Str(text) => Rational::parse(text)
+            .ok_or_else(|| LoxError::object_error(&format!("Function 'exact' cannot read \"{text}\" as a number.")))?,
+        value if value.is_number() => value.to_rational()
+            .ok_or_else(|| LoxError::object_error("Function 'exact' need a finite number."))?,
+        _ => return Err(LoxError::object_error("Function 'exact' need a number or a string.")),
+    };
+    Ok(Object::Rat(value))
+}
+
+fn rational(_interpreter: &mut Interpreter, arguments: &[Object]) -> Result<Object, LoxError> {
+    match (arguments[0].to_bigint(), arguments[1].to_bigint()) {
+        (Some(numerator), Some(denominator)) => Rational::new(numerator, denominator).map(Object::Rat)
+            .ok_or_else(|| LoxError::object_error("Division by zero in function 'rational'.")),
+        _ => Err(LoxError::object_error("Function 'rational' need two integer arguments.")),
+    }
+}
+
+fn float(_interpreter: &mut Interpreter, arguments: &[Object]) -> Result<Object, LoxError> {
+    arguments[0].to_f64().map(Object::Num).ok_or_else(|| LoxError::object_error("Function 'float' need a number."))
+}
+
+// Rounded toward zero.
+fn int(_interpreter: &mut Interpreter, arguments: &[Object]) -> Result<Object, LoxError> {
+    match &arguments[0] {
+        value @ (Object::Int(_) | Object::Big(_)) => Ok(value.clone()),
+        Object::Rat(value) => Ok(Object::integer(value.trunc())),
+        Object::Num(value) => Rational::from_f64(*value).map(|value| Object::integer(value.trunc()))
+            .ok_or_else(|| LoxError::object_error("Function 'int' need a finite number.")),
+        _ => Err(LoxError::object_error("Function 'int' need a number.")),
+    }
+}
+
+// A number written out with `places` decimals, rounded half to even.
+fn fixed(_interpreter: &mut Interpreter, arguments: &[Object]) -> Result<Object, LoxError> {
+    let places = match &arguments[1] {
+        Object::Int(places @ 0..=1000) => *places as u32,
+        _ => return Err(LoxError::object_error("Function 'fixed' need between 0 and 1000 places.")),
+    };
+    match arguments[0].to_rational() {
+        Some(value) => Ok(Object::Str(value.to_fixed(places))),
+        None => Err(LoxError::object_error("Function 'fixed' need a finite number.")),
+    }
+}
+
+// Strings are sequences of Unicode scalar values, so lengths and positions count those,
+// never bytes: `len("héllo")` is 5.
+pub const STRINGS: &[NativeFunction] = &[
+    NativeFunction { name: "len", arity: 1, function: len },
+    NativeFunction { name: "substring", arity: 3, function: substring },
+    NativeFunction { name: "slice", arity: 3, function: slice },
+    NativeFunction { name: "indexOf", arity: 2, function: index_of },
+    NativeFunction { name: "replace", arity: 3, function: replace },
+    NativeFunction { name: "trim", arity: 1, function: trim },
+    NativeFunction { name: "upper", arity: 1, function: upper },
+    NativeFunction { name: "lower", arity: 1, function: lower },
+    NativeFunction { name: "startsWith", arity: 2, function: starts_with },
+    NativeFunction { name: "endsWith", arity: 2, function: ends_with },
+    NativeFunction { name: "repeat", arity: 2, function: repeat },
+    NativeFunction { name: "split", arity: 2, function: split },
+    NativeFunction { name: "chars", arity: 1, function: chars },
+    NativeFunction { name: "join", arity: 2, function: join },
+];
+
+// The longest string `repeat` makes, in bytes.
+const MAX_STRING_BYTES: usize = 1 << 30;
+
+// Also the number of values in a list.
+fn len(_interpreter: &mut Interpreter, arguments: &[Object]) -> Result<Object, LoxError> {
+    match &arguments[0] {
+        Object::Str(text) => Ok(Object::Int(text.chars().count() as i64)),
+        Object::List(list) => Ok(Object::Int(list.borrow().len() as i64)),
+        _ => Err(LoxError::object_error("Function 'len' need a string or a list.")),
+    }
+}
+
+// The byte offset of the scalar value at `index`, or the length of `text` past its last one.
+fn offset(text: &str, index: usize) -> usize {
+    text.char_indices().nth(index).map_or(text.len(), |(offset, _)| offset)
+}
+
+// The values from `start` up to but not including `end`, which must lie within the string.
+fn substring(_interpreter: &mut Interpreter, arguments: &[Object]) -> Result<Object, LoxError> {
+    let [Object::Str(text), Object::Int(start), Object::Int(end)] = arguments else {
+        return Err(LoxError::object_error("Function 'substring' need a string and two integers."));
+    };
+    let length = text.chars().count() as i64;
+    if !(0 <= *start && start <= end && *end <= length) {
+        return Err(LoxError::object_error(&format!("Range {start} to {end} out of bounds 0 to {length} in function 'substring'.")));
+    }
+    Ok(Object::Str(text[offset(text, *start as usize)..offset(text, *end as usize)].to_owned()))
+}
+
+// Like `substring`, but negative positions count from the end and the range is cut to fit,
+// so `slice(s, -3, len(s))` is the last three values of `s`, or all of a shorter one.
+fn slice(_interpreter: &mut Interpreter, arguments: &[Object]) -> Result<Object, LoxError> {
+    let [Object::Str(text), Object::Int(start), Object::Int(end)] = arguments else {
+        return Err(LoxError::object_error("Function 'slice' need a string and two integers."));
+    };
+    let length = text.chars().count() as i64;
+    let position = |index: i64| match index < 0 {
+        true => (length + index).max(0),
+        false => index.min(length),
+    } as usize;
+    let (start, end) = (position(*start), position(*end));
+    match start < end {
+        true => Ok(Object::Str(text[offset(text, start)..offset(text, end)].to_owned())),
+        false => Ok(Object::Str(String::new())),
+    }
+}
+
+// The position of the first occurrence of `part`, or -1.
+fn index_of(_interpreter: &mut Interpreter, arguments: &[Object]) -> Result<Object, LoxError> {
+    let [Object::Str(text), Object::Str(part)] = arguments else {
+        return Err(LoxError::object_error("Function 'indexOf' need two strings."));
+    };
+    match text.find(part.as_str()) {
+        Some(offset) => Ok(Object::Int(text[..offset].chars().count() as i64)),
+        None => Ok(Object::Int(-1)),
+    }
+}
+
+// Every occurrence, left to right.
+fn replace(_interpreter: &mut Interpreter, arguments: &[Object]) -> Result<Object, LoxError> {
+    let [Object::Str(text), Object::Str(from), Object::Str(to)] = arguments else {
+        return Err(LoxError::object_error("Function 'replace' need three strings."));
+    };
+    if from.is_empty() {
+        return Err(LoxError::object_error("Function 'replace' cannot replace an empty string."));
+    }
+    Ok(Object::Str(text.replace(from.as_str(), to)))
+}
+
+fn trim(_interpreter: &mut Interpreter, arguments: &[Object]) -> Result<Object, LoxError> {
+    match &arguments[0] {
+        Object::Str(text) => Ok(Object::Str(text.trim().to_owned())),
+        _ => Err(LoxError::object_error("Function 'trim' need a string.")),
+    }
+}
+
+// Case mappings are Unicode's, which may change the length: `upper("ß")` is "SS".
+fn upper(_interpreter: &mut Interpreter, arguments: &[Object]) -> Result<Object, LoxError> {
+    match &arguments[0] {
+        Object::Str(text) => Ok(Object::Str(text.to_uppercase())),
+        _ => Err(LoxError::object_error("Function 'upper' need a string.")),
+    }
+}
+
+fn lower(_interpreter: &mut Interpreter, arguments: &[Object]) -> Result<Object, LoxError> {
+    match &arguments[0] {
+        Object::Str(text) => Ok(Object::Str(text.to_lowercase())),
+        _ => Err(LoxError::object_error("Function 'lower' need a string.")),
+    }
+}
+
+fn starts_with(_interpreter: &mut Interpreter, arguments: &[Object]) -> Result<Object, LoxError> {
+    match arguments {
+        [Object::Str(text), Object::Str(prefix)] => Ok(Object::Bool(text.starts_with(prefix.as_str()))),
+        _ => Err(LoxError::object_error("Function 'startsWith' need two strings.")),
+    }
+}
+
+fn ends_with(_interpreter: &mut Interpreter, arguments: &[Object]) -> Result<Object, LoxError> {
+    match arguments {
+        [Object::Str(text), Object::Str(suffix)] => Ok(Object::Bool(text.ends_with(suffix.as_str()))),
+        _ => Err(LoxError::object_error("Function 'endsWith' need two strings.")),
+    }
+}
+
+fn repeat(_interpreter: &mut Interpreter, arguments: &[Object]) -> Result<Object, LoxError> {
+    let [Object::Str(text), Object::Int(count @ 0..)] = arguments else {
+        return Err(LoxError::object_error("Function 'repeat' need a string and a non-negative integer."));
+    };
+    match usize::try_from(*count).ok().and_then(|count| text.len().checked_mul(count)) {
+        Some(bytes) if bytes <= MAX_STRING_BYTES => Ok(Object::Str(text.repeat(*count as usize))),
+        _ => Err(LoxError::object_error("Result of function 'repeat' is too large.")),
+    }
+}
+
+fn strings<'a>(values: impl Iterator<Item = &'a str>) -> Object {
+    Object::list(values.map(|value| Object::Str(value.to_owned())).collect())
+}
+
+// The parts of `s` between separators, empty parts included.
+fn split(_interpreter: &mut Interpreter, arguments: &[Object]) -> Result<Object, LoxError> {
+    let [Object::Str(text), Object::Str(separator)] = arguments else {
+        return Err(LoxError::object_error("Function 'split' need two strings."));
+    };
+    if separator.is_empty() {
+        return Err(LoxError::object_error("Function 'split' cannot split on an empty string; use 'chars'."));
+    }
+    Ok(strings(text.split(separator.as_str())))
+}
+
+// Each scalar value of `s`, as a string of its own.
+fn chars(_interpreter: &mut Interpreter, arguments: &[Object]) -> Result<Object, LoxError> {
+    match &arguments[0] {
+        Object::Str(text) => Ok(strings(text.split_inclusive(|_| true))),
+        _ => Err(LoxError::object_error("Function 'chars' need a string.")),
+    }
+}
+
+// The strings of a list with a separator between each two, so `join(split(s, t), t)` is `s`.
+fn join(_interpreter: &mut Interpreter, arguments: &[Object]) -> Result<Object, LoxError> {
+    let [Object::List(list), Object::Str(separator)] = arguments else {
+        return Err(LoxError::object_error("Function 'join' need a list and a string."));
+    };
+    let parts = list.borrow().iter().map(|value| match value {
+        Object::Str(text) => Ok(text.clone()),
+        _ => Err(LoxError::object_error("Function 'join' need a list of strings.")),
+    }).collect::<Result<Vec<_>, _>>()?;
+    let bytes = parts.iter().map(String::len).sum::<usize>() + separator.len() * parts.len().saturating_sub(1);
+    match bytes <= MAX_STRING_BYTES {
+        true => Ok(Object::Str(parts.join(separator))),
+        false => Err(LoxError::object_error("Result of function 'join' is too large.")),
+    }
+}
+
+// Lists hold values of any type and are shared, not copied: `push` and `set` change the list
+// for everything that holds it. Positions count from 0.
+pub const LISTS: &[NativeFunction] = &[
+    NativeFunction { name: "list", arity: 0, function: list },
+    NativeFunction { name: "push", arity: 2, function: push },
+    NativeFunction { name: "pop", arity: 1, function: pop },
+    NativeFunction { name: "get", arity: 2, function: get },
+    NativeFunction { name: "set", arity: 3, function: set },
+];
+
+fn list(_interpreter: &mut Interpreter, _arguments: &[Object]) -> Result<Object, LoxError> {
+    Ok(Object::list(Vec::new()))
+}
+
+// Adds a value at the end.
+fn push(_interpreter: &mut Interpreter, arguments: &[Object]) -> Result<Object, LoxError> {
+    match &arguments[0] {
+        Object::List(list) => {
+            list.borrow_mut().push(arguments[1].clone());
+            Ok(Object::Nil)
+        },
+        _ => Err(LoxError::object_error("Function 'push' need a list.")),
+    }
+}
+
+// Removes the last value and returns it.
+fn pop(_interpreter: &mut Interpreter, arguments: &[Object]) -> Result<Object, LoxError> {
+    match &arguments[0] {
+        Object::List(list) => list.borrow_mut().pop()
+            .ok_or_else(|| LoxError::object_error("Function 'pop' cannot take from an empty list.")),
+        _ => Err(LoxError::object_error("Function 'pop' need a list.")),
+    }
+}
+
+// The list and the position an index names in it, which must lie within the list.
+fn position<'a>(arguments: &'a [Object], function: &str) -> Result<(&'a RefCell<Vec<Object>>, usize), LoxError> {
+    let [Object::List(list), Object::Int(index), ..] = arguments else {
+        return Err(LoxError::object_error(&format!("Function '{function}' need a list and an integer.")));
+    };
+    let length = list.borrow().len();
+    match usize::try_from(*index) {
+        Ok(index) if index < length => Ok((list, index)),
+        _ => Err(LoxError::object_error(&format!("Index {index} out of bounds 0 to {length} in function '{function}'."))),
+    }
+}
+
+fn get(_interpreter: &mut Interpreter, arguments: &[Object]) -> Result<Object, LoxError> {
+    let (list, index) = position(arguments, "get")?;
+    let value = list.borrow()[index].clone();
+    Ok(value)
+}
+
+// Replaces the value at a position.
+fn set(_interpreter: &mut Interpreter, arguments: &[Object]) -> Result<Object, LoxError> {
+    let (list, index) = position(arguments, "set")?;
+    list.borrow_mut()[index] = arguments[2].clone();
+    Ok(Object::Nil)
+}
+
+// Calls a function of one argument with each value, stopping at the first error. Natives that
+// produce values as they read them, such as `eachLine`, use this rather than build a list.
+pub fn each(interpreter: &mut Interpreter, callback: &Object, function: &str, values: impl Iterator<Item = Result<Object, LoxError>>) -> Result<Object, LoxError> {
+    let Object::Func(callback) = callback else {
+        return Err(LoxError::object_error(&format!("Function '{function}' need a function to call.")));
+    };
+    if callback.arity() != 1 {
+        return Err(LoxError::object_error(&format!("Function '{function}' need a function of one argument.")));
+    }
+    for value in values {
+        callback.call(interpreter, &[value?])?;
+    }
+    Ok(Object::Nil)
+}
+
+pub const MATH_CONSTANTS: &[(&str, f64)] = &[
+    ("pi", std::f64::consts::PI),
+    ("e", std::f64::consts::E),
+    ("inf", f64::INFINITY),
+    ("nan", f64::NAN),
+];
+
+// Functions such as `sqrt` work on floats and always return one. `abs`, `min`, `max` and
+// the roundings keep integers and rationals exact.
+pub const MATH: &[NativeFunction] = &[
+    NativeFunction { name: "sqrt", arity: 1, function: sqrt },
+    NativeFunction { name: "pow", arity: 2, function: pow },
+    NativeFunction { name: "abs", arity: 1, function: abs },
+    NativeFunction { name: "floor", arity: 1, function: floor },
+    NativeFunction { name: "ceil", arity: 1, function: ceil },
+    NativeFunction { name: "round", arity: 1, function: round },
+    NativeFunction { name: "trunc", arity: 1, function: trunc },
+    NativeFunction { name: "min", arity: 2, function: min },
+    NativeFunction { name: "max", arity: 2, function: max },
+    NativeFunction { name: "sin", arity: 1, function: sin },
+    NativeFunction { name: "cos", arity: 1, function: cos },
+    NativeFunction { name: "tan", arity: 1, function: tan },
+    NativeFunction { name: "atan2", arity: 2, function: atan2 },
+    NativeFunction { name: "log", arity: 1, function: log },
+    NativeFunction { name: "exp", arity: 1, function: exp },
+    NativeFunction { name: "isNaN", arity: 1, function: is_nan },
+    NativeFunction { name: "isFinite", arity: 1, function: is_finite },
+];
+
+fn float_argument(value: &Object, name: &str) -> Result<f64, LoxError> {
+    value.to_f64().ok_or_else(|| LoxError::object_error(&format!("Function '{name}' need a number.")))
+}
+
+// Out of their domain, such as `sqrt(-1)` or `log(0)`, these give NaN or an infinity as floats do.
+fn unary_float(arguments: &[Object], name: &str, function: fn(f64) -> f64) -> Result<Object, LoxError> {
+    Ok(Object::Num(function(float_argument(&arguments[0], name)?)))
+}
+
+fn binary_float(arguments: &[Object], name: &str, function: fn(f64, f64) -> f64) -> Result<Object, LoxError> {
+    match (arguments[0].to_f64(), arguments[1].to_f64()) {
+        (Some(a), Some(b)) => Ok(Object::Num(function(a, b))),
+        _ => Err(LoxError::object_error(&format!("Function '{name}' need two numbers."))),
+    }
+}
+
+fn sqrt(_interpreter: &mut Interpreter, arguments: &[Object]) -> Result<Object, LoxError> {
+    unary_float(arguments, "sqrt", f64::sqrt)
+}
+
+// Always a float, unlike `**`, which keeps integers exact.
+fn pow(_interpreter: &mut Interpreter, arguments: &[Object]) -> Result<Object, LoxError> {
+    binary_float(arguments, "pow", f64::powf)
+}
+
+fn sin(_interpreter: &mut Interpreter, arguments: &[Object]) -> Result<Object, LoxError> {
+    unary_float(arguments, "sin", f64::sin)
+}
+
+fn cos(_interpreter: &mut Interpreter, arguments: &[Object]) -> Result<Object, LoxError> {
+    unary_float(arguments, "cos", f64::cos)
+}
+
+fn tan(_interpreter: &mut Interpreter, arguments: &[Object]) -> Result<Object, LoxError> {
+    unary_float(arguments, "tan", f64::tan)
+}
+
+// The angle of the point (x, y), given as `atan2(y, x)`.
+fn atan2(_interpreter: &mut Interpreter, arguments: &[Object]) -> Result<Object, LoxError> {
+    binary_float(arguments, "atan2", f64::atan2)
+}
+
+// The natural logarithm.
+fn log(_interpreter: &mut Interpreter, arguments: &[Object]) -> Result<Object, LoxError> {
+    unary_float(arguments, "log", f64::ln)
+}
+
+fn exp(_interpreter: &mut Interpreter, arguments: &[Object]) -> Result<Object, LoxError> {
+    unary_float(arguments, "exp", f64::exp)
+}
+
+fn abs(_interpreter: &mut Interpreter, arguments: &[Object]) -> Result<Object, LoxError> {
+    match &arguments[0] {
+        Object::Int(value) => Ok(Object::integer(BigInt::from(*value).abs())),
+        Object::Big(value) => Ok(Object::Big(value.abs())),
+        Object::Rat(value) if value.numerator().is_negative() => Ok(Object::Rat(value.neg())),
+        value @ Object::Rat(_) => Ok(value.clone()),
+        Object::Num(value) => Ok(Object::Num(value.abs())),
+        _ => Err(LoxError::object_error("Function 'abs' need a number.")),
+    }
+}
+
+// Integers are already rounded, rationals round to integers and floats to floats.
+fn rounding(arguments: &[Object], name: &str, float: fn(f64) -> f64, exact: fn(&Rational) -> BigInt) -> Result<Object, LoxError> {
+    match &arguments[0] {
+        value @ (Object::Int(_) | Object::Big(_)) => Ok(value.clone()),
+        Object::Rat(value) => Ok(Object::integer(exact(value))),
+        Object::Num(value) => Ok(Object::Num(float(*value))),
+        _ => Err(LoxError::object_error(&format!("Function '{name}' need a number."))),
+    }
+}
+
+fn floor(_interpreter: &mut Interpreter, arguments: &[Object]) -> Result<Object, LoxError> {
+    rounding(arguments, "floor", f64::floor, Rational::floor)
+}
+
+fn ceil(_interpreter: &mut Interpreter, arguments: &[Object]) -> Result<Object, LoxError> {
+    rounding(arguments, "ceil", f64::ceil, Rational::ceil)
+}
+
+// Halves round away from zero: `round(2.5)` is 3.0 and `round(-2.5)` is -3.0.
+fn round(_interpreter: &mut Interpreter, arguments: &[Object]) -> Result<Object, LoxError> {
+    rounding(arguments, "round", f64::round, Rational::round)
+}
+
+fn trunc(_interpreter: &mut Interpreter, arguments: &[Object]) -> Result<Object, LoxError> {
+    rounding(arguments, "trunc", f64::trunc, Rational::trunc)
+}
+
+// One of the two numbers as it is, or NaN when either is NaN.
+fn extremum(arguments: &[Object], name: &str, first: fn(&Object, Object) -> Result<Object, LoxError>) -> Result<Object, LoxError> {
+    let (a, b) = (&arguments[0], &arguments[1]);
+    if !a.is_number() || !b.is_number() {
+        return Err(LoxError::object_error(&format!("Function '{name}' need two numbers.")));
+    }
+    if let Some(nan) = arguments.iter().find(|value| matches!(value, Object::Num(value) if value.is_nan())) {
+        return Ok(nan.clone());
+    }
+    match first(a, b.clone())? {
+        Object::Bool(true) => Ok(a.clone()),
+        _ => Ok(b.clone()),
+    }
+}
+
+fn min(_interpreter: &mut Interpreter, arguments: &[Object]) -> Result<Object, LoxError> {
+    extremum(arguments, "min", Object::lessequal)
+}
+
+fn max(_interpreter: &mut Interpreter, arguments: &[Object]) -> Result<Object, LoxError> {
+    extremum(arguments, "max", Object::greaterequal)
+}
+
+fn is_nan(_interpreter: &mut Interpreter, arguments: &[Object]) -> Result<Object, LoxError> {
+    match &arguments[0] {
+        Object::Num(value) => Ok(Object::Bool(value.is_nan())),
+        value if value.is_number() => Ok(Object::Bool(false)),
+        _ => Err(LoxError::object_error("Function 'isNaN' need a number.")),
+    }
+}
+
+fn is_finite(_interpreter: &mut Interpreter, arguments: &[Object]) -> Result<Object, LoxError> {
+    match &arguments[0] {
+        Object::Num(value) => Ok(Object::Bool(value.is_finite())),
+        value if value.is_number() => Ok(Object::Bool(true)),
+        _ => Err(LoxError::object_error("Function 'isFinite' need a number.")),
+    }
+}
+
+// Without a call to `seed`, every run draws different numbers.
+pub const RANDOM: &[NativeFunction] = &[
+    NativeFunction { name: "random", arity: 0, function: random },
+    NativeFunction { name: "randomInt", arity: 2, function: random_int },
+    NativeFunction { name: "seed", arity: 1, function: seed },
+    NativeFunction { name: "shuffle", arity: 1, function: shuffle },
+    NativeFunction { name: "choice", arity: 1, function: choice },
+];
+
+// A float from 0 up to but not including 1.
+fn random(interpreter: &mut Interpreter, _arguments: &[Object]) -> Result<Object, LoxError> {
+    Ok(Object::Num(interpreter.random.next_f64()))
+}
+
+// An integer from `low` to `high`, both included.
+fn random_int(interpreter: &mut Interpreter, arguments: &[Object]) -> Result<Object, LoxError> {
+    let [Object::Int(low), Object::Int(high)] = arguments else {
+        return Err(LoxError::object_error("Function 'randomInt' need two integers."));
+    };
+    if low > high {
+        return Err(LoxError::object_error(&format!("Empty range {low} to {high} in function 'randomInt'.")));
+    }
+    let offset = match u64::try_from((*high as i128 - *low as i128) + 1) {
+        Ok(span) => interpreter.random.below(span),
+        Err(_) => interpreter.random.next_u64(),     // the span of every i64
+    };
+    Ok(Object::Int(low.wrapping_add(offset as i64)))
+}
+
+// Puts the values of a list in a random order, in place, by Fisher-Yates.
+fn shuffle(interpreter: &mut Interpreter, arguments: &[Object]) -> Result<Object, LoxError> {
+    let Object::List(list) = &arguments[0] else {
+        return Err(LoxError::object_error("Function 'shuffle' need a list."));
+    };
+    let mut values = list.borrow_mut();
+    for i in (1..values.len()).rev() {
+        let j = interpreter.random.below(i as u64 + 1) as usize;
+        values.swap(i, j);
+    }
+    Ok(Object::Nil)
+}
+
+// One value of a list, each as likely as the others.
+fn choice(interpreter: &mut Interpreter, arguments: &[Object]) -> Result<Object, LoxError> {
+    let Object::List(list) = &arguments[0] else {
+        return Err(LoxError::object_error("Function 'choice' need a list."));
+    };
+    let values = list.borrow();
+    match values.len() {
+        0 => Err(LoxError::object_error("Function 'choice' cannot choose from an empty list.")),
+        length => Ok(values[interpreter.random.below(length as u64) as usize].clone()),
+    }
+}
+
+fn seed(interpreter: &mut Interpreter, arguments: &[Object]) -> Result<Object, LoxError> {
+    match &arguments[0] {
+        Object::Int(seed) => {
+            interpreter.random = Random::new(*seed as u64);
+            Ok(Object::Nil)
+        },
+        _ => Err(LoxError::object_error("Function 'seed' need an integer.")),
+    }
+}
+
+// Reading what the user types, or whatever the interpreter's input was set to.
+pub const INPUT: &[NativeFunction] = &[
+    NativeFunction { name: "readLine", arity: 0, function: read_line },
+    NativeFunction { name: "input", arity: 1, function: input },
+    NativeFunction { name: "readAll", arity: 0, function: read_all },
+];
+
+// The next line without its line break, or nil at the end of the input. Output is flushed
+// first, so whatever the script asked is on screen.
+fn next_line(interpreter: &mut Interpreter, function: &str) -> Result<Object, LoxError> {
+    interpreter.flush().map_err(|e| LoxError::object_error(&format!("Cannot write output: {e}.")))?;
+    let mut line = String::new();
+    match interpreter.with_input(|input| input.read_line(&mut line)) {
+        Ok(0) => Ok(Object::Nil),
+        Ok(_) => {
+            let end = line.trim_end_matches('\n').trim_end_matches('\r').len();
+            line.truncate(end);
+            Ok(Object::Str(line))
+        },
+        Err(e) => Err(LoxError::object_error(&format!("Function '{function}' failed: {e}."))),
+    }
+}
+
+fn read_line(interpreter: &mut Interpreter, _arguments: &[Object]) -> Result<Object, LoxError> {
+    next_line(interpreter, "readLine")
+}
+
+// Shows the prompt, without a line break, and reads the answer as `readLine` does.
+fn input(interpreter: &mut Interpreter, arguments: &[Object]) -> Result<Object, LoxError> {
+    interpreter.write_output(&arguments[0].to_string(), Stream::Stdout)?;
+    next_line(interpreter, "input")
+}
+
+// Everything left, which is an empty string at the end of the input.
+fn read_all(interpreter: &mut Interpreter, _arguments: &[Object]) -> Result<Object, LoxError> {
+    interpreter.flush().map_err(|e| LoxError::object_error(&format!("Cannot write output: {e}.")))?;
+    let mut text = String::new();
+    match interpreter.with_input(|input| input.read_to_string(&mut text)) {
+        Ok(_) => Ok(Object::Str(text)),
+        Err(e) => Err(LoxError::object_error(&format!("Function 'readAll' failed: {e}."))),
+    }
+}
+
+// `print` with more control: `write` adds no line break, and `eprint` writes a line to stderr.
+pub const OUTPUT: &[NativeFunction] = &[
+    NativeFunction { name: "write", arity: 1, function: write },
+    NativeFunction { name: "eprint", arity: 1, function: eprint },
+];
+
+fn write(interpreter: &mut Interpreter, arguments: &[Object]) -> Result<Object, LoxError> {
+    interpreter.write_output(&arguments[0].to_string(), Stream::Stdout)?;
+    Ok(Object::Nil)
+}
+
+// To stderr, or to the debugger's client as error output.
+fn eprint(interpreter: &mut Interpreter, arguments: &[Object]) -> Result<Object, LoxError> {
+    interpreter.write_output(&format!("{}\n", arguments[0]), Stream::Stderr)?;
+    Ok(Object::Nil)
+}
@@ -1,29 +1,43 @@
 use crate::callable::*;
 use crate::error::LoxError;
+use crate::bigint::BigInt;
+use crate::rational::Rational;
 
+use std::cell::RefCell;
 use std::rc::Rc;
 
+use std::cmp::Ordering;
 use std::fmt;
-use std::ops::{Add, Sub, Mul, Div, Neg, Not};
+use std::ops::{Add, Sub, Mul, Div, Rem, Neg, Not};
 
-#[derive(Clone)] 
+#[derive(Clone)]
 pub enum Object {
+    Int(i64),
+    Big(BigInt),        // only for integers that do not fit in an `Int`
+    Rat(Rational),
     Num(f64),
     Str(String),
     Bool(bool),
     Func(Rc<dyn LoxCallable>),
+    List(Rc<RefCell<Vec<Object>>>),     // shared, so a change made through one variable shows in all
     Nil,
 }
 
+// Both operands of an arithmetic operator, converted to the wider of their two types:
+// integers widen to big integers, those to rationals, and everything to floats.
+enum Operands {
+    Int(i64, i64),
+    Big(BigInt, BigInt),
+    Rat(Rational, Rational),
+    Num(f64, f64),
+}
+
+// Powers and left shifts larger than this many bits are refused rather than computed.
+const MAX_POWER_BITS: u64 = 1 << 20;
+
 impl fmt::Debug for Object{
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        match self {
-            Self::Num(x) => write!(f, "{x}"),
-            Self::Str(x) => write!(f, "{x}"),
-            Self::Bool(x) => if *x {write!(f, "true")} else {write!(f, "false")},
-            Self::Nil => write!(f, "nil"),
-            Self::Func(x) => write!(f, "{}", x)
-        }
+        write!(f, "{self}")
     }
 }
 
@@ -31,43 +45,238 @@ impl PartialEq for Object {
     fn eq(&self, other: &Self) -> bool {
         match (self, other) {
             (Self::Str(x), Self::Str(y)) => x == y,
-            (Self::Num(x), Self::Num(y)) => x == y, 
             (Self::Bool(x), Self::Bool(y)) => x == y,
             (Self::Nil, Self::Nil) => true,
             (Self::Func(x), Self::Func(y)) => {
                 x.arity() == y.arity() && x.name() == y.name()
             },
-            _ => false,
+            (Self::List(x), Self::List(y)) => Rc::ptr_eq(x, y),
+            _ => self.numeric_cmp(other) == Some(Some(Ordering::Equal)),
         }
     }
 }
 
 impl Object {
-    pub fn greater(&self, rhs: Self) -> Result<Self, LoxError> {
-        match (self, &rhs) {
-            (Object::Num(a), Object::Num(b)) => Ok(Object::Bool(a > b)),
-            _ => Err(LoxError::object_error("Operator '>' need two Num operands."))
+    // Big integers that fit are always turned back into plain ones.
+    pub fn integer(value: BigInt) -> Self {
+        match value.to_i64() {
+            Some(value) => Object::Int(value),
+            None => Object::Big(value),
         }
     }
 
-    pub fn greaterequal(&self, rhs: Self) -> Result<Self, LoxError> {
-        match (self, &rhs) {
-            (Object::Num(a), Object::Num(b)) => Ok(Object::Bool(a >= b)),
-            _ => Err(LoxError::object_error("Operator '>=' need two Num operands."))
+    pub fn list(values: Vec<Object>) -> Self {
+        Object::List(Rc::new(RefCell::new(values)))
+    }
+
+    pub fn is_number(&self) -> bool {
+        self.rank().is_some()
+    }
+
+    pub fn to_f64(&self) -> Option<f64> {
+        match self {
+            Object::Int(x) => Some(*x as f64),
+            Object::Big(x) => Some(x.to_f64()),
+            Object::Rat(x) => Some(x.to_f64()),
+            Object::Num(x) => Some(*x),
+            _ => None,
         }
     }
 
-    pub fn less(&self, rhs: Self) -> Result<Self, LoxError> {
-        match (self, &rhs) {
-            (Object::Num(a), Object::Num(b)) => Ok(Object::Bool(a < b)),
-            _ => Err(LoxError::object_error("Operator '<' need two Num operands."))
+    pub fn to_bigint(&self) -> Option<BigInt> {
+        match self {
+            Object::Int(x) => Some(BigInt::from(*x)),
+            Object::Big(x) => Some(x.clone()),
+            _ => None,
+        }
+    }
+
+    pub fn to_rational(&self) -> Option<Rational> {
+        match self {
+            Object::Rat(x) => Some(x.clone()),
+            Object::Num(x) => Rational::from_f64(*x),
+            _ => self.to_bigint().map(Rational::from),
+        }
+    }
+
+    // How wide a numeric type is, in the order `Operands` converts them.
+    fn rank(&self) -> Option<u8> {
+        match self {
+            Object::Int(_) => Some(0),
+            Object::Big(_) => Some(1),
+            Object::Rat(_) => Some(2),
+            Object::Num(_) => Some(3),
+            _ => None,
+        }
+    }
+
+    fn operands(&self, rhs: &Self) -> Option<Operands> {
+        match self.rank()?.max(rhs.rank()?) {
+            0 => match (self, rhs) {
+                (Object::Int(a), Object::Int(b)) => Some(Operands::Int(*a, *b)),
+                _ => None,
+            },
+            1 => Some(Operands::Big(self.to_bigint()?, rhs.to_bigint()?)),
+            2 => Some(Operands::Rat(self.to_rational()?, rhs.to_rational()?)),
+            _ => Some(Operands::Num(self.to_f64()?, rhs.to_f64()?)),
+        }
+    }
+
+    // Numbers of different types compare by their exact values, so a finite float is taken as the
+    // fraction it holds rather than rounding the other side to a float. The outer `None` is for
+    // non-numbers, the inner one for NaN.
+    fn numeric_cmp(&self, rhs: &Self) -> Option<Option<Ordering>> {
+        match (self, rhs) {
+            (Object::Num(a), Object::Num(b)) => Some(a.partial_cmp(b)),
+            (Object::Num(a), b) if !a.is_finite() => b.is_number().then(|| a.partial_cmp(&0.0)),
+            (a, Object::Num(b)) if !b.is_finite() => a.is_number().then(|| 0.0.partial_cmp(b)),
+            _ => Some(match self.operands(rhs)? {
+                Operands::Int(a, b) => Some(a.cmp(&b)),
+                Operands::Big(a, b) => Some(a.cmp(&b)),
+                Operands::Rat(a, b) => Some(a.cmp(&b)),
+                Operands::Num(..) => Some(self.to_rational()?.cmp(&rhs.to_rational()?)),
+            }),
         }
     }
 
+    // `None` when a float is NaN. Strings compare by their scalar values, first difference first.
+    fn compare(&self, rhs: &Self, operator: &str) -> Result<Option<Ordering>, LoxError> {
+        if let (Object::Str(a), Object::Str(b)) = (self, rhs) {
+            return Ok(Some(a.cmp(b)));
+        }
+        match self.numeric_cmp(rhs) {
+            Some(order) => Ok(order),
+            None => Err(LoxError::object_error(&format!("Operator '{operator}' need two Num or two Str operands."))),
+        }
+    }
+
+    pub fn greater(&self, rhs: Self) -> Result<Self, LoxError> {
+        Ok(Object::Bool(matches!(self.compare(&rhs, ">")?, Some(Ordering::Greater))))
+    }
+
+    pub fn greaterequal(&self, rhs: Self) -> Result<Self, LoxError> {
+        Ok(Object::Bool(matches!(self.compare(&rhs, ">=")?, Some(Ordering::Greater | Ordering::Equal))))
+    }
+
+    pub fn less(&self, rhs: Self) -> Result<Self, LoxError> {
+        Ok(Object::Bool(matches!(self.compare(&rhs, "<")?, Some(Ordering::Less))))
+    }
+
     pub fn lessequal(&self, rhs: Self) -> Result<Self, LoxError> {
-        match (self, &rhs) {
-            (Object::Num(a), Object::Num(b)) => Ok(Object::Bool(a <= b)),
-            _ => Err(LoxError::object_error("Operator '<=' need two Num operands."))
+        Ok(Object::Bool(matches!(self.compare(&rhs, "<=")?, Some(Ordering::Less | Ordering::Equal))))
+    }
+
+    // An integer raised to a negative power is a float; a rational stays exact for integer powers.
+    pub fn power(&self, rhs: Self) -> Result<Self, LoxError> {
+        match self.operands(&rhs) {
+            Some(Operands::Int(a, b)) if b >= 0 => match u32::try_from(b).ok().and_then(|b| a.checked_pow(b)) {
+                Some(result) => Ok(Object::Int(result)),
+                None => big_power(&BigInt::from(a), &BigInt::from(b)),
+            },
+            Some(Operands::Int(a, b)) => Ok(Object::Num((a as f64).powf(b as f64))),
+            Some(Operands::Big(a, b)) if !b.is_negative() => big_power(&a, &b),
+            Some(Operands::Big(a, b)) => Ok(Object::Num(a.to_f64().powf(b.to_f64()))),
+            Some(Operands::Rat(a, b)) => match (*b.denominator() == BigInt::from(1)).then(|| b.numerator().to_i64()).flatten() {
+                Some(exponent) if a.pow_bits(exponent) > MAX_POWER_BITS => Err(too_large("**")),
+                Some(exponent) => a.pow(exponent).map(Object::Rat)
+                    .ok_or_else(|| LoxError::object_error("Division by zero in operator '**'.")),
+                None => Ok(Object::Num(a.to_f64().powf(b.to_f64()))),
+            },
+            Some(Operands::Num(a, b)) => Ok(Object::Num(a.powf(b))),
+            _ => Err(LoxError::object_error("Operator '**' need two Num operands."))
+        }
+    }
+
+    // Division rounded toward negative infinity, so that `a == (a ~/ b) * b + a % b`.
+    pub fn floor_div(&self, rhs: Self) -> Result<Self, LoxError> {
+        let division_by_zero = || LoxError::object_error("Division by zero in operator '~/'.");
+        match self.operands(&rhs) {
+            Some(Operands::Int(_, 0)) => Err(division_by_zero()),
+            Some(Operands::Int(a, b)) => match a.checked_div(b) {
+                Some(quotient) if a % b != 0 && (a < 0) != (b < 0) => Ok(Object::Int(quotient - 1)),
+                Some(quotient) => Ok(Object::Int(quotient)),
+                None => Ok(Object::integer(BigInt::from(a).neg())),
+            },
+            Some(Operands::Big(a, b)) => a.div_mod_floor(&b).map(|(quotient, _)| Object::integer(quotient)).ok_or_else(division_by_zero),
+            Some(Operands::Rat(a, b)) => a.div(&b).map(|quotient| Object::integer(quotient.floor())).ok_or_else(division_by_zero),
+            Some(Operands::Num(_, 0.0)) => Err(division_by_zero()),
+            // A finite number over an infinite one is a fraction on the side of zero its sign picks.
+            Some(Operands::Num(a, b)) if a.is_finite() && b.is_infinite() => match a == 0.0 || (a < 0.0) == (b < 0.0) {
+                true => Ok(Object::Num(0.0)),
+                false => Ok(Object::Num(-1.0)),
+            },
+            Some(Operands::Num(a, b)) => Ok(Object::Num((a / b).floor())),
+            _ => Err(LoxError::object_error("Operator '~/' need two Num operands."))
+        }
+    }
+
+    pub fn bit_and(&self, rhs: Self) -> Result<Self, LoxError> {
+        self.bitwise(&rhs, "&", |a, b| a & b, |a, b| a & b)
+    }
+
+    pub fn bit_or(&self, rhs: Self) -> Result<Self, LoxError> {
+        self.bitwise(&rhs, "|", |a, b| a | b, |a, b| a | b)
+    }
+
+    pub fn bit_xor(&self, rhs: Self) -> Result<Self, LoxError> {
+        self.bitwise(&rhs, "^", |a, b| a ^ b, |a, b| a ^ b)
+    }
+
+    pub fn bit_not(&self) -> Result<Self, LoxError> {
+        match self {
+            Object::Int(x) => Ok(Object::Int(!x)),
+            _ => Ok(Object::integer(self.integral("~")?.neg().sub(&BigInt::from(1)))),
+        }
+    }
+
+    // Integers act as two's complement with as many bits as they need, so nothing is lost.
+    fn bitwise(&self, rhs: &Self, operator: &str, small: fn(i64, i64) -> i64, digits: fn(u32, u32) -> u32) -> Result<Self, LoxError> {
+        match (self, rhs) {
+            (Object::Int(a), Object::Int(b)) => Ok(Object::Int(small(*a, *b))),
+            _ => Ok(Object::integer(self.integral(operator)?.bitwise(&rhs.integral(operator)?, digits))),
+        }
+    }
+
+    // Integers that outgrow 64 bits become big integers; `>>` rounds toward negative infinity.
+    pub fn shift_left(&self, rhs: Self) -> Result<Self, LoxError> {
+        let amount = rhs.shift_amount("<<")?;
+        if let Object::Int(value) = self {
+            if let Some(shifted) = value.checked_shl(amount as u32).filter(|shifted| amount < 64 && shifted >> amount == *value) {
+                return Ok(Object::Int(shifted));
+            }
+        }
+        let value = self.integral("<<")?;
+        match value.bits().saturating_add(amount) > MAX_POWER_BITS {
+            _ if value.is_zero() => Ok(Object::Int(0)),
+            true => Err(too_large("<<")),
+            false => Ok(Object::integer(value.shl(amount))),
+        }
+    }
+
+    pub fn shift_right(&self, rhs: Self) -> Result<Self, LoxError> {
+        let amount = rhs.shift_amount(">>")?;
+        match self {
+            Object::Int(value) => Ok(Object::Int(value >> amount.min(63))),
+            _ => Ok(Object::integer(self.integral(">>")?.shr(amount))),
+        }
+    }
+
+    // The bitwise operators work on integers of any size, and on floats with no fractional part.
+    fn integral(&self, operator: &str) -> Result<BigInt, LoxError> {
+        match self {
+            Object::Int(x) => Ok(BigInt::from(*x)),
+            Object::Big(x) => Ok(x.clone()),
+            Object::Num(x) if x.fract() == 0.0 => Ok(Rational::from_f64(*x).expect("A float with no fractional part is finite.").trunc()),
+            _ => Err(LoxError::object_error(&format!("Operator '{operator}' need integral Num operands."))),
+        }
+    }
+
+    // Amounts too large for 64 bits are as good as endless: only `>>` can take them.
+    fn shift_amount(&self, operator: &str) -> Result<u64, LoxError> {
+        let amount = self.integral(operator)?;
+        match amount.is_negative() {
+            true => Err(LoxError::object_error(&format!("Negative shift amount in operator '{operator}'."))),
+            false => Ok(amount.to_i64().map_or(u64::MAX, |amount| amount as u64)),
         }
     }
 
@@ -87,23 +296,72 @@ impl Object {
     }
 }
 
+fn big_power(base: &BigInt, exponent: &BigInt) -> Result<Object, LoxError> {
+    match exponent.to_i64().and_then(|exponent| u32::try_from(exponent).ok()) {
+        Some(exponent) if base.pow_bits(exponent) <= MAX_POWER_BITS => Ok(Object::integer(base.pow(exponent))),
+        _ => Err(too_large("**")),
+    }
+}
+
+fn too_large(operator: &str) -> LoxError {
+    LoxError::object_error(&format!("Result of operator '{operator}' is too large."))
+}
+
+// Plain integers that overflow carry on as big integers.
+fn widen(result: Option<i64>, a: i64, b: i64, operation: fn(&BigInt, &BigInt) -> BigInt) -> Object {
+    match result {
+        Some(result) => Object::Int(result),
+        None => Object::integer(operation(&BigInt::from(a), &BigInt::from(b))),
+    }
+}
+
+// Floats always show a fractional part or an exponent, so `3.0` does not print like `3`.
 impl fmt::Display for Object {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
-            Self::Num(x) => write!(f, "{x}"),
+            Self::Int(x) => write!(f, "{x}"),
+            Self::Big(x) => write!(f, "{x}"),
+            Self::Rat(x) => write!(f, "{x}"),
+            Self::Num(x) => write!(f, "{x:?}"),
             Self::Str(x) => write!(f, "{x}"),
             Self::Bool(x) => if *x {write!(f, "true")} else {write!(f, "false")},
             Self::Nil => write!(f, "nil"),
-            Self::Func(x) => write!(f, "{}", x)
+            Self::Func(x) => write!(f, "{}", x),
+            Self::List(x) => write_list(f, x, &mut Vec::new()),
         }
     }
 }
 
+// Strings in a list are quoted, so `["1"]` and `[1]` can be told apart. `open` holds the lists
+// being written, so a list that holds itself is written as `[...]` there.
+fn write_list(f: &mut fmt::Formatter<'_>, list: &Rc<RefCell<Vec<Object>>>, open: &mut Vec<*const RefCell<Vec<Object>>>) -> fmt::Result {
+    if open.contains(&Rc::as_ptr(list)) {
+        return write!(f, "[...]");
+    }
+    open.push(Rc::as_ptr(list));
+    write!(f, "[")?;
+    for (index, value) in list.borrow().iter().enumerate() {
+        if index > 0 {
+            write!(f, ", ")?;
+        }
+        match value {
+            Object::Str(x) => write!(f, "{x:?}")?,
+            Object::List(x) => write_list(f, x, open)?,
+            x => write!(f, "{x}")?,
+        }
+    }
+    open.pop();
+    write!(f, "]")
+}
+
 impl Neg for Object {
-    type Output = Result<Object, LoxError>; 
+    type Output = Result<Object, LoxError>;
 
     fn neg(self) -> Self::Output {
         match self {
+            Object::Int(val) => Ok(val.checked_neg().map_or_else(|| Object::integer(BigInt::from(val).neg()), Object::Int)),
+            Object::Big(val) => Ok(Object::integer(val.neg())),
+            Object::Rat(val) => Ok(Object::Rat(val.neg())),
             Object::Num(val) => Ok(Object::Num(-val)),
             _ => Err(LoxError::object_error("Prefix operator '-' need Num operand."))
         }
@@ -111,7 +369,7 @@ impl Neg for Object {
 }
 
 impl Not for Object {
-    type Output = Result<Object, LoxError>; 
+    type Output = Result<Object, LoxError>;
 
     fn not(self) -> Self::Output {
         match self {
@@ -125,13 +383,16 @@ impl Add for Object {
     type Output = Result<Object, LoxError>;
 
     fn add(self, rhs: Self) -> Self::Output {
-        match (self, rhs) {
-            (Object::Num(left), Object::Num(right)) => Ok(Object::Num(left + right)),
-            (Object::Str(left), Object::Str(right)) => Ok(Object::Str(format!("{left}{right}"))),
-            (Object::Num(left), Object::Str(right)) => Ok(Object::Str(format!("{left}{right}"))),
-            (Object::Str(left), Object::Num(right)) => Ok(Object::Str(format!("{left}{right}"))),
+        match (self.operands(&rhs), self, rhs) {
+            (Some(Operands::Int(left, right)), ..) => Ok(widen(left.checked_add(right), left, right, BigInt::add)),
+            (Some(Operands::Big(left, right)), ..) => Ok(Object::integer(left.add(&right))),
+            (Some(Operands::Rat(left, right)), ..) => Ok(Object::Rat(left.add(&right))),
+            (Some(Operands::Num(left, right)), ..) => Ok(Object::Num(left + right)),
+            (_, Object::Str(left), Object::Str(right)) => Ok(Object::Str(format!("{left}{right}"))),
+            (_, left, Object::Str(right)) if left.is_number() => Ok(Object::Str(format!("{left}{right}"))),
+            (_, Object::Str(left), right) if right.is_number() => Ok(Object::Str(format!("{left}{right}"))),
             _ => Err(LoxError::object_error("Unexpected Type of operands for operator '+'."))
-        } 
+        }
     }
 }
 
@@ -139,10 +400,13 @@ impl Sub for Object {
     type Output = Result<Object, LoxError>;
 
     fn sub(self, rhs: Self) -> Self::Output {
-        match (self, rhs) {
-            (Object::Num(left), Object::Num(right)) => Ok(Object::Num(left - right)),
+        match self.operands(&rhs) {
+            Some(Operands::Int(left, right)) => Ok(widen(left.checked_sub(right), left, right, BigInt::sub)),
+            Some(Operands::Big(left, right)) => Ok(Object::integer(left.sub(&right))),
+            Some(Operands::Rat(left, right)) => Ok(Object::Rat(left.sub(&right))),
+            Some(Operands::Num(left, right)) => Ok(Object::Num(left - right)),
             _ => Err(LoxError::object_error("Operator '-' need two Num operands."))
-        } 
+        }
     }
 }
 
@@ -150,19 +414,64 @@ impl Mul for Object {
     type Output = Result<Object, LoxError>;
 
     fn mul(self, rhs: Self) -> Self::Output {
-        match (self, rhs) {
-             (Object::Num(left), Object::Num(right)) => Ok(Object::Num(left * right)),
+        match self.operands(&rhs) {
+            Some(Operands::Int(left, right)) => Ok(widen(left.checked_mul(right), left, right, BigInt::mul)),
+            Some(Operands::Big(left, right)) => Ok(Object::integer(left.mul(&right))),
+            Some(Operands::Rat(left, right)) => Ok(Object::Rat(left.mul(&right))),
+            Some(Operands::Num(left, right)) => Ok(Object::Num(left * right)),
             _ => Err(LoxError::object_error("Operator '*' need two Num operands."))
         }
     }
 }
 
+// The remainder takes the sign of the divisor, as in floored division: `-7 % 3` is 2.
+impl Rem for Object {
+    type Output = Result<Object, LoxError>;
+
+    fn rem(self, rhs: Self) -> Self::Output {
+        let division_by_zero = || LoxError::object_error("Division by zero in operator '%'.");
+        match self.operands(&rhs) {
+            Some(Operands::Int(_, 0)) => Err(division_by_zero()),
+            Some(Operands::Int(left, right)) => {
+                let remainder = left.wrapping_rem(right);
+                match remainder != 0 && (remainder < 0) != (right < 0) {
+                    true => Ok(Object::Int(remainder + right)),
+                    false => Ok(Object::Int(remainder)),
+                }
+            },
+            Some(Operands::Big(left, right)) => left.div_mod_floor(&right).map(|(_, remainder)| Object::integer(remainder)).ok_or_else(division_by_zero),
+            Some(Operands::Rat(left, right)) => {
+                let quotient = left.div(&right).ok_or_else(division_by_zero)?.floor();
+                Ok(Object::Rat(left.sub(&right.mul(&Rational::from(quotient)))))
+            },
+            Some(Operands::Num(_, 0.0)) => Err(division_by_zero()),
+            // `%` on floats keeps the sign of `left`, and is exact, infinite divisors included.
+            Some(Operands::Num(left, right)) => {
+                let remainder = left % right;
+                match remainder != 0.0 && (remainder < 0.0) != (right < 0.0) {
+                    true => Ok(Object::Num(remainder + right)),
+                    false => Ok(Object::Num(remainder)),
+                }
+            },
+            _ => Err(LoxError::object_error("Operator '%' need two Num operands."))
+        }
+    }
+}
+
+// `/` divides integers as floats; `~/` is the integer division. Rationals divide exactly.
 impl Div for Object {
     type Output = Result<Object, LoxError>;
 
     fn div(self, rhs: Self) -> Self::Output {
-        match (self, rhs) {
-             (Object::Num(left), Object::Num(right)) => Ok(Object::Num(left / right)),
+        match self.operands(&rhs) {
+            Some(Operands::Int(left, right)) => Ok(Object::Num(left as f64 / right as f64)),
+            Some(Operands::Big(left, right)) => match Rational::new(left.clone(), right) {
+                Some(quotient) => Ok(Object::Num(quotient.to_f64())),
+                None => Ok(Object::Num(left.to_f64() / 0.0)),
+            },
+            Some(Operands::Rat(left, right)) => left.div(&right).map(Object::Rat)
+                .ok_or_else(|| LoxError::object_error("Division by zero in operator '/'.")),
+            Some(Operands::Num(left, right)) => Ok(Object::Num(left / right)),
             _ => Err(LoxError::object_error("Operator '/' need two Num operands."))
         }
     }
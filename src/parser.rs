@@ -1,21 +1,56 @@
 use crate::{error::LoxError, expr::*, token::*, object::Object, stmt::*};
 
+use std::collections::HashMap;
 use std::rc::Rc;
+
+// Source details that the desugared tree no longer carries, keyed by node like `Interpreter::locals`.
+// Only tools that work on the source (the formatter, the JSON export) need them.
+#[derive(Default)]
+pub struct Trivia {
+    pub for_loops: HashMap<Stmt, ForClauses>,
+    pub spans: HashMap<Stmt, (usize, usize)>,       // indices of the first and last token of each statement
+    pub expr_spans: HashMap<Expr, (usize, usize)>,  // and of each expression
+}
+
+// Which clauses of a `for` loop were written, so the loop can be told apart from the blocks it desugars to.
+pub struct ForClauses {
+    pub initializer: bool,
+    pub condition: bool,
+    pub increment: bool,
+}
+
 pub struct Parser {
     tokens: Vec<Token>,
     current: usize,
     had_error: bool,
+    trivia: Trivia,
 }
 
 impl Parser {
     pub fn new(tokens: Vec<Token>) -> Self {
-        Self { tokens, current: 0, had_error: false }
+        Self { tokens, current: 0, had_error: false, trivia: Trivia::default() }
     }
 
     pub fn success(&self) -> bool {
         !self.had_error
     }
 
+    pub fn trivia(&self) -> &Trivia {
+        &self.trivia
+    }
+
+    pub fn tokens(&self) -> &[Token] {
+        &self.tokens
+    }
+
+    // The line each statement starts on. Statements made up while desugaring have none.
+    pub fn statement_lines(&self) -> HashMap<Stmt, usize> {
+        self.trivia.spans.iter().map(|(stmt, (first, _))| {
+            let token = &self.tokens[*first];
+            (stmt.clone(), token.line - token.lexeme.matches('\n').count())
+        }).collect()
+    }
+
     fn peek(&self) -> Option<Token> {
         self.tokens.get(self.current).cloned()
     }
@@ -48,6 +83,7 @@ impl Parser {
     // }
 
     fn assignment(&mut self) -> Result<Expr, LoxError> {
+        let start = self.current;
         let expr = self.or()?;
 
         match self.peek() {
@@ -58,7 +94,9 @@ impl Parser {
 
                 if let Expr::Variable(variable) = expr {
                     let name = variable.name.clone();
-                    return Ok(Expr::Assign(Rc::new(AssignExpr { name, value: Box::new(value) })))
+                    let expr = Expr::Assign(Rc::new(AssignExpr { name, value: Box::new(value) }));
+                    self.record_expr_span(start, &expr);
+                    return Ok(expr)
                 } 
                 self.had_error = true;
                 LoxError::parse_error(&equals, "Invalid Assignment Target.");
@@ -69,29 +107,89 @@ impl Parser {
     }
 
     fn or(&mut self) -> Result<Expr, LoxError> {
+        let start = self.current;
         let mut expr = self.and()?;
         while matches!(self.peek(), Some(token) if token.ttype == TokenType::Or) {
             let operator = self.peek().unwrap();
             self.advance();
             let right = Box::new(self.and()?);
-            expr = Expr::Logical(Rc::new(LogicalExpr { left: Box::new(expr), operator, right }))
+            expr = Expr::Logical(Rc::new(LogicalExpr { left: Box::new(expr), operator, right }));
+            self.record_expr_span(start, &expr);
         }
         Ok(expr)
     }
 
     fn and(&mut self) -> Result<Expr, LoxError> {
-        let mut expr = self.equality()?;
+        let start = self.current;
+        let mut expr = self.bit_or()?;
         while matches!(self.peek(), Some(token) if token.ttype == TokenType::And) {
             let operator = self.peek().unwrap();
             self.advance();
             let right = Box::new(self.and()?);
-            expr = Expr::Logical(Rc::new(LogicalExpr { left: Box::new(expr), operator, right }))
+            expr = Expr::Logical(Rc::new(LogicalExpr { left: Box::new(expr), operator, right }));
+            self.record_expr_span(start, &expr);
         }
      
         Ok(expr)
     }
 
+    // The bitwise operators bind as in C: looser than equality, so `a & b == c` is `a & (b == c)`.
+    fn bit_or(&mut self) -> Result<Expr, LoxError> {
+        let start = self.current;
+        let mut expr = self.bit_xor()?;
+
+        while matches!(self.peek(), Some(token) if token.ttype == TokenType::Pipe) {
+            let operator = self.advance();
+            let right = self.bit_xor()?;
+            expr = Expr::Binary(Rc::new(BinaryExpr {
+                left: Box::new(expr),
+                operator,
+                right: Box::new(right),
+            }));
+            self.record_expr_span(start, &expr);
+        }
+
+        Ok(expr)
+    }
+
+    fn bit_xor(&mut self) -> Result<Expr, LoxError> {
+        let start = self.current;
+        let mut expr = self.bit_and()?;
+
+        while matches!(self.peek(), Some(token) if token.ttype == TokenType::Caret) {
+            let operator = self.advance();
+            let right = self.bit_and()?;
+            expr = Expr::Binary(Rc::new(BinaryExpr {
+                left: Box::new(expr),
+                operator,
+                right: Box::new(right),
+            }));
+            self.record_expr_span(start, &expr);
+        }
+
+        Ok(expr)
+    }
+
+    fn bit_and(&mut self) -> Result<Expr, LoxError> {
+        let start = self.current;
+        let mut expr = self.equality()?;
+
+        while matches!(self.peek(), Some(token) if token.ttype == TokenType::Ampersand) {
+            let operator = self.advance();
+            let right = self.equality()?;
+            expr = Expr::Binary(Rc::new(BinaryExpr {
+                left: Box::new(expr),
+                operator,
+                right: Box::new(right),
+            }));
+            self.record_expr_span(start, &expr);
+        }
+
+        Ok(expr)
+    }
+
     fn equality(&mut self) -> Result<Expr, LoxError> {
+        let start = self.current;
         let mut expr = self.comparison()?;
 
         while matches!(self.peek(), Some(token) if matches!(token.ttype, TokenType::Equal | TokenType::BangEqual))
@@ -104,30 +202,52 @@ impl Parser {
                 operator,
                 right: Box::new(right),
             }));
+            self.record_expr_span(start, &expr);
         }
 
         Ok(expr)
     }
 
     fn comparison(&mut self) -> Result<Expr, LoxError> {
-        let mut expr = self.term()?;
+        let start = self.current;
+        let mut expr = self.shift()?;
 
         while matches!(self.peek(), Some(token) if matches!(token.ttype, TokenType::Greater | TokenType::GreaterEqual | TokenType::Less | TokenType::LessEqual))
         {
             let operator = self.peek().unwrap();
             self.advance();
+            let right = self.shift()?;
+            expr = Expr::Binary(Rc::new(BinaryExpr {
+                left: Box::new(expr),
+                operator,
+                right: Box::new(right),
+            }));
+            self.record_expr_span(start, &expr);
+        }
+
+        Ok(expr)
+    }
+
+    fn shift(&mut self) -> Result<Expr, LoxError> {
+        let start = self.current;
+        let mut expr = self.term()?;
+
+        while matches!(self.peek(), Some(token) if matches!(token.ttype, TokenType::LessLess | TokenType::GreaterGreater)) {
+            let operator = self.advance();
             let right = self.term()?;
             expr = Expr::Binary(Rc::new(BinaryExpr {
                 left: Box::new(expr),
                 operator,
                 right: Box::new(right),
             }));
+            self.record_expr_span(start, &expr);
         }
 
         Ok(expr)
     }
 
     fn term(&mut self) -> Result<Expr, LoxError> {
+        let start = self.current;
         let mut expr = self.factor()?;
 
         while matches!(self.peek(), Some(token) if matches!(token.ttype, TokenType::Plus | TokenType::Minus)) {
@@ -139,15 +259,17 @@ impl Parser {
                 operator,
                 right: Box::new(right),
             }));
+            self.record_expr_span(start, &expr);
         }
 
         Ok(expr)
     }
 
     fn factor(&mut self) -> Result<Expr, LoxError> {
+        let start = self.current;
         let mut expr = self.unary()?;
 
-        while matches!(self.peek(), Some(token) if matches!(token.ttype, TokenType::Star | TokenType::Slash)) {
+        while matches!(self.peek(), Some(token) if matches!(token.ttype, TokenType::Star | TokenType::Slash | TokenType::Percent | TokenType::TildeSlash)) {
             let operator = self.peek().unwrap();
             self.advance();
             let right = self.unary()?;
@@ -156,33 +278,61 @@ impl Parser {
                 operator,
                 right: Box::new(right),
             }));
+            self.record_expr_span(start, &expr);
         }
 
         Ok(expr)
     }
 
     fn unary(&mut self) -> Result<Expr, LoxError> {
-        if matches!(self.peek(), Some(token) if matches!(token.ttype, TokenType::Bang | TokenType::Minus)) {
+        if matches!(self.peek(), Some(token) if matches!(token.ttype, TokenType::Bang | TokenType::Minus | TokenType::Tilde)) {
+            let start = self.current;
             let operator = self.peek().unwrap();
             self.advance();
             let right = self.unary()?;
-            return Ok(Expr::Unary(Rc::new(UnaryExpr {
+            let expr = Expr::Unary(Rc::new(UnaryExpr {
+                operator,
+                right: Box::new(right),
+            }));
+            self.record_expr_span(start, &expr);
+            return Ok(expr);
+        }
+
+        self.power()
+    }
+
+    // `**` binds tighter than a unary minus on its left, so `-2 ** 2` is -4, and is right-associative.
+    // Its right operand may carry a sign of its own, as in `2 ** -1`.
+    fn power(&mut self) -> Result<Expr, LoxError> {
+        let start = self.current;
+        let expr = self.call()?;
+
+        if matches!(self.peek(), Some(token) if token.ttype == TokenType::StarStar) {
+            let operator = self.advance();
+            let right = self.unary()?;
+            let expr = Expr::Binary(Rc::new(BinaryExpr {
+                left: Box::new(expr),
                 operator,
                 right: Box::new(right),
-            })));
+            }));
+            self.record_expr_span(start, &expr);
+            return Ok(expr);
         }
 
-        self.call()
+        Ok(expr)
     }
 
     fn call(&mut self) -> Result<Expr, LoxError> {
+        let start = self.current;
         let mut expr = self.primary()?;
+        self.record_expr_span(start, &expr);
 
         loop {
             match self.peek() {
                 Some(token) if token.ttype == TokenType::LeftParen => {
                     self.advance();
                     expr = self.finish_call(expr)?;
+                    self.record_expr_span(start, &expr);
                 },
                 _ => break,
             }
@@ -229,6 +379,7 @@ impl Parser {
                     TokenType::Number | TokenType::String => Ok(Expr::Literal(Rc::new(LiteralExpr {
                         value: token.literal,
                     }))),
+                    TokenType::Interpolation => self.interpolation(token),
                     TokenType::Identifier => Ok(Expr::Variable(Rc::new(VariableExpr { name: token }))),
                     TokenType::LeftParen => {
                         let expr = self.expression()?;
@@ -246,6 +397,36 @@ impl Parser {
         }
     }
 
+    // `"a ${b} c"` is scanned as Interpolation("a "), b, InterpolationEnd(" c"). The parts
+    // alternate between string segments and the expressions between them.
+    fn interpolation(&mut self, first: Token) -> Result<Expr, LoxError> {
+        let mut parts = vec![self.segment(first)];
+        loop {
+            parts.push(self.expression()?);
+            match self.peek() {
+                Some(token) if token.ttype == TokenType::Interpolation => {
+                    self.advance();
+                    parts.push(self.segment(token));
+                },
+                Some(token) if token.ttype == TokenType::InterpolationEnd => {
+                    self.advance();
+                    parts.push(self.segment(token));
+                    break;
+                },
+                Some(token) => return Err(LoxError::parse_error(&token, "Expect '}' after interpolated expression.")),
+                None => unreachable!("The tokens always end with Eof, which the arm above reports."),
+            }
+        }
+        Ok(Expr::Interpolation(Rc::new(InterpolationExpr { parts })))
+    }
+
+    // A string segment of an interpolation, just consumed.
+    fn segment(&mut self, token: Token) -> Expr {
+        let expr = Expr::Literal(Rc::new(LiteralExpr { value: token.literal }));
+        self.record_expr_span(self.current - 1, &expr);
+        expr
+    }
+
     fn consume(&mut self, ttype: TokenType, message: &str) -> Result<Token, LoxError> {
         match self.peek() {
             Some(token) if token.ttype == ttype => {
@@ -259,29 +440,47 @@ impl Parser {
         }
     }
 
+    // Skips to the start of the next statement. Always moves forward, so parsing can go on after an error.
     fn synchronize(&mut self) {
+        let start = self.current;
         while let Some(token) = self.peek() {
             match token.ttype {
-                TokenType::Eof
-                | TokenType::Class
+                TokenType::Eof => break,
+                TokenType::SemiColon => {
+                    self.advance();
+                    break;
+                },
+                TokenType::Class
                 | TokenType::Fun
                 | TokenType::Var
                 | TokenType::For
                 | TokenType::If
                 | TokenType::While
                 | TokenType::Print
-                | TokenType::Return => break,
+                | TokenType::Return if self.current > start => break,
                 _ => {
                     self.advance();
                 }
             }
-            if matches!(self.peek(), Some(token) if matches!(token.ttype, TokenType::SemiColon)) {
-                break;
-            }
         }
     }
 
     fn statement(&mut self) -> Result<Stmt, LoxError> {
+        let start = self.current;
+        let stmt = self.statement_kind()?;
+        self.record_span(start, &stmt);
+        Ok(stmt)
+    }
+
+    fn record_span(&mut self, start: usize, stmt: &Stmt) {
+        self.trivia.spans.insert(stmt.clone(), (start, self.current - 1));
+    }
+
+    fn record_expr_span(&mut self, start: usize, expr: &Expr) {
+        self.trivia.expr_spans.insert(expr.clone(), (start, self.current - 1));
+    }
+
+    fn statement_kind(&mut self) -> Result<Stmt, LoxError> {
         match self.peek() {
             Some(token) if token.ttype == TokenType::Break => {
                 self.advance();
@@ -290,7 +489,7 @@ impl Parser {
             }
             Some(token) if token.ttype == TokenType::Print => {
                 self.advance();
-                self.print_statement()
+                self.print_statement(token)
             },
             Some(token) if token.ttype == TokenType::LeftBrace => {
                 self.advance();
@@ -340,6 +539,7 @@ impl Parser {
             _ => None,
         };
 
+        let has_condition = matches!(self.peek(), Some(token) if token.ttype != TokenType::SemiColon);
         let condition = match self.peek() {
             Some(token) if token.ttype != TokenType::SemiColon => {
                 self.expression()?
@@ -359,6 +559,7 @@ impl Parser {
         self.consume(TokenType::RightParen, "Expect ')' after for clauses.")?;
 
         let mut body = self.statement()?;
+        let has_increment = increment.is_some();
 
         if let Some(increment) = increment {
             body = Stmt::Block(Rc::new(BlockStmt { statements: vec![body, Stmt::Expression(Rc::new(ExpressionStmt { expression: increment })) ] }));
@@ -366,10 +567,12 @@ impl Parser {
 
         body = Stmt::While(Rc::new(WhileStmt { condition, body: Box::new(body) }));
         
+        let has_initializer = initializer.is_some();
         if let Some(initializer) = initializer {
             body = Stmt::Block(Rc::new(BlockStmt { statements: vec![initializer, body] }));
         }
 
+        self.trivia.for_loops.insert(body.clone(), ForClauses { initializer: has_initializer, condition: has_condition, increment: has_increment });
         Ok(body)
     }
 
@@ -414,10 +617,10 @@ impl Parser {
         Ok(statements)
     }
 
-    fn print_statement(&mut self) -> Result<Stmt, LoxError> {
+    fn print_statement(&mut self, keyword: Token) -> Result<Stmt, LoxError> {
         let expression = self.expression()?;
         self.consume(TokenType::SemiColon, "Expect ';' after value.")?;
-        Ok(Stmt::Print(Rc::new(PrintStmt { expression })))
+        Ok(Stmt::Print(Rc::new(PrintStmt { keyword, expression })))
     }
 
     fn expression_statement(&mut self) -> Result<Stmt, LoxError> {
@@ -427,6 +630,7 @@ impl Parser {
     }
 
     fn declaration(&mut self) -> Result<Stmt, LoxError> {
+        let start = self.current;
         let res = match self.peek() {
             Some(token) if token.ttype == TokenType::Var => {
                 self.advance();
@@ -439,8 +643,9 @@ impl Parser {
             _ => self.statement()
         };
 
-        if res.is_err() {
-            self.synchronize();
+        match &res {
+            Ok(stmt) => self.record_span(start, stmt),
+            Err(_) => self.synchronize(),
         }
         res
     }
@@ -487,7 +692,10 @@ impl Parser {
     pub fn parse(&mut self) -> Result<Vec<Stmt>, LoxError> {
         let mut statements = Vec::new();
         while matches!(self.peek(), Some(token) if !matches!(token.ttype, TokenType::Eof)) {
-            statements.push(self.declaration()?);
+            match self.declaration() {
+                Ok(stmt) => statements.push(stmt),
+                Err(_) => self.had_error = true,
+            }
         }
         Ok(statements)
     }
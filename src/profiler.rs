@@ -0,0 +1,159 @@
+use std::{cell::RefCell, collections::{BTreeMap, HashMap}, fs, io::{self, Write}, path::PathBuf, rc::Rc, time::{Duration, Instant}};
+
+use crate::{stmt::Stmt, object::Object, token::Token, error::LoxError, environment::Environment,
+    callable::LoxCallable, hook::ExecutionHook};
+
+const SCRIPT: &str = "<script>";
+
+// An instrumenting profiler. Time between two events is charged to the line that was
+// running and to the call stack it ran in; the hook's own overhead is included.
+pub struct Profiler {
+    lines: HashMap<Stmt, usize>,
+    output: PathBuf,
+    frames: Vec<ProfileFrame>,
+    start: Instant,
+    last: Instant,
+    functions: HashMap<String, FunctionProfile>,
+    line_profiles: BTreeMap<usize, LineProfile>,
+    stacks: HashMap<String, Duration>,     // self time by `;`-joined call stack
+}
+
+struct ProfileFrame {
+    function: String,
+    stack: String,
+    line: usize,
+    start: Instant,
+}
+
+#[derive(Default)]
+struct FunctionProfile {
+    calls: usize,
+    total: Duration,
+    own: Duration,
+    active: usize,      // recursive calls in progress, so their time is only counted once
+}
+
+#[derive(Default)]
+struct LineProfile {
+    hits: usize,
+    time: Duration,
+}
+
+impl ExecutionHook for Profiler {
+    fn statement(&mut self, stmt: &Stmt, _environment: &Rc<RefCell<Environment>>) -> Result<(), LoxError> {
+        // A block starts on the line of its first statement, which is counted on its own.
+        if matches!(stmt, Stmt::Block(_)) {
+            return Ok(());
+        }
+        if let Some(&line) = self.lines.get(stmt) {
+            self.tick();
+            self.frame().line = line;
+            self.line_profiles.entry(line).or_default().hits += 1;
+        }
+        Ok(())
+    }
+
+    fn enter(&mut self, function: &dyn LoxCallable, _paren: &Token, _arguments: &[Object]) {
+        self.tick();
+        let name = match function.line() {
+            Some(line) => format!("{}:{line}", function.name()),
+            None => format!("{} (native)", function.name()),
+        };
+        let caller = self.frames.last().expect("The script frame is never popped.");
+        let frame = ProfileFrame {
+            stack: format!("{};{name}", caller.stack),
+            line: caller.line,      // natives have no lines of their own
+            function: name,
+            start: self.last,
+        };
+
+        let profile = self.functions.entry(frame.function.clone()).or_default();
+        profile.calls += 1;
+        profile.active += 1;
+        self.frames.push(frame);
+    }
+
+    fn leave(&mut self, _result: &Result<Object, LoxError>) {
+        self.tick();
+        if self.frames.len() > 1 {
+            let frame = self.frames.pop().expect("Checked above.");
+            let profile = self.functions.get_mut(&frame.function).expect("Entered before.");
+            profile.active -= 1;
+            if profile.active == 0 {
+                profile.total += self.last - frame.start;
+            }
+        }
+    }
+
+    fn finish(&mut self) -> io::Result<()> {
+        self.tick();
+        let script = self.functions.entry(SCRIPT.to_owned()).or_default();
+        script.total = self.last - self.start;
+
+        let mut stacks: Vec<_> = self.stacks.iter().collect();
+        stacks.sort();
+        let mut folded = io::BufWriter::new(fs::File::create(&self.output)?);
+        for (stack, time) in stacks {
+            if time.as_micros() > 0 {
+                writeln!(folded, "{stack} {}", time.as_micros())?;
+            }
+        }
+        folded.flush()?;
+
+        self.summary(&mut io::stderr().lock())
+    }
+}
+
+impl Profiler {
+    // `output` receives the folded stacks; the summary goes to stderr.
+    pub fn new(lines: HashMap<Stmt, usize>, output: PathBuf) -> Self {
+        let now = Instant::now();
+        let script = ProfileFrame { function: SCRIPT.to_owned(), stack: SCRIPT.to_owned(), line: 0, start: now };
+        let mut functions = HashMap::new();
+        functions.insert(SCRIPT.to_owned(), FunctionProfile { calls: 1, ..Default::default() });
+        Self {
+            lines, output, frames: vec![script], start: now, last: now,
+            functions, line_profiles: BTreeMap::new(), stacks: HashMap::new(),
+        }
+    }
+
+    // Charges the time since the last event to where the program was.
+    fn tick(&mut self) {
+        let now = Instant::now();
+        let elapsed = now - self.last;
+        self.last = now;
+
+        let frame = self.frames.last().expect("The script frame is never popped.");
+        *self.stacks.entry(frame.stack.clone()).or_default() += elapsed;
+        self.functions.entry(frame.function.clone()).or_default().own += elapsed;
+        if frame.line > 0 {
+            self.line_profiles.entry(frame.line).or_default().time += elapsed;
+        }
+    }
+
+    fn frame(&mut self) -> &mut ProfileFrame {
+        self.frames.last_mut().expect("The script frame is never popped.")
+    }
+
+    fn summary(&self, out: &mut impl Write) -> io::Result<()> {
+        let mut functions: Vec<_> = self.functions.iter().collect();
+        functions.sort_by(|a, b| b.1.own.cmp(&a.1.own).then(a.0.cmp(b.0)));
+        writeln!(out, "{:<30} {:>10} {:>12} {:>12}", "function", "calls", "total ms", "self ms")?;
+        for (name, profile) in functions {
+            writeln!(out, "{name:<30} {:>10} {:>12.3} {:>12.3}", profile.calls, millis(profile.total), millis(profile.own))?;
+        }
+
+        let mut lines: Vec<_> = self.line_profiles.iter().collect();
+        lines.sort_by(|a, b| b.1.time.cmp(&a.1.time).then(a.0.cmp(b.0)));
+        writeln!(out)?;
+        writeln!(out, "{:<30} {:>10} {:>12}", "line", "hits", "self ms")?;
+        for (line, profile) in lines {
+            writeln!(out, "{line:<30} {:>10} {:>12.3}", profile.hits, millis(profile.time))?;
+        }
+        Ok(())
+    }
+}
+
+fn millis(time: Duration) -> f64 {
+    time.as_secs_f64() * 1000.0
+}
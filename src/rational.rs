@@ -0,0 +1,279 @@
+use std::{cmp::Ordering, fmt};
+
+use crate::bigint::BigInt;
+
+// An exact fraction, always in lowest terms with a positive denominator.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Rational {
+    numerator: BigInt,
+    denominator: BigInt,
+}
+
+impl Rational {
+    // `None` for a zero denominator.
+    pub fn new(numerator: BigInt, denominator: BigInt) -> Option<Self> {
+        if denominator.is_zero() {
+            return None;
+        }
+        let (numerator, denominator) = match denominator.is_negative() {
+            true => (numerator.neg(), denominator.neg()),
+            false => (numerator, denominator),
+        };
+        if numerator.is_zero() {
+            return Some(Self { numerator, denominator: BigInt::from(1) });
+        }
+        let divisor = numerator.gcd(&denominator);
+        Some(Self { numerator: numerator.div_rem(&divisor)?.0, denominator: denominator.div_rem(&divisor)?.0 })
+    }
+
+    pub fn numerator(&self) -> &BigInt {
+        &self.numerator
+    }
+
+    pub fn denominator(&self) -> &BigInt {
+        &self.denominator
+    }
+
+    // The exact value of a float, which is always a fraction over a power of two.
+    pub fn from_f64(value: f64) -> Option<Self> {
+        if !value.is_finite() {
+            return None;
+        }
+        let bits = value.to_bits();
+        let exponent = ((bits >> 52) & 0x7ff) as i64;
+        let fraction = (bits & ((1 << 52) - 1)) as i64;
+        let (mantissa, exponent) = match exponent {
+            0 => (fraction, -1074),
+            _ => (fraction | 1 << 52, exponent - 1075),
+        };
+        let mantissa = BigInt::from(if value < 0.0 { -mantissa } else { mantissa });
+        match exponent >= 0 {
+            true => Self::new(mantissa.shl(exponent as u64), BigInt::from(1)),
+            false => Self::new(mantissa, BigInt::from(1).shl(exponent.unsigned_abs())),
+        }
+    }
+
+    // A decimal such as `-12.50`, or a fraction such as `1/3`.
+    pub fn parse(text: &str) -> Option<Self> {
+        let text = text.trim();
+        if let Some((numerator, denominator)) = text.split_once('/') {
+            return Self::new(BigInt::parse(numerator.trim())?, BigInt::parse(denominator.trim())?);
+        }
+        let (whole, fraction) = text.split_once('.').unwrap_or((text, ""));
+        if whole.is_empty() || whole == "-" {
+            return None;
+        }
+        let numerator = BigInt::parse(&format!("{whole}{fraction}"))?;
+        Self::new(numerator, BigInt::from(10).pow(fraction.len() as u32))
+    }
+
+    // The nearest float, from a quotient scaled to keep 64 significant bits. A remainder sets the
+    // last bit of the quotient's magnitude, so a value just past a tie does not round as the tie would.
+    pub fn to_f64(&self) -> f64 {
+        let shift = 64 + self.denominator.bits() as i64 - self.numerator.bits() as i64;
+        let quotient = match shift >= 0 {
+            true => self.numerator.shl(shift as u64).div_rem(&self.denominator),
+            false => self.numerator.div_rem(&self.denominator.shl(shift.unsigned_abs())),
+        };
+        let (quotient, remainder) = quotient.expect("The denominator is never zero.");
+        let magnitude = match remainder.is_zero() {
+            true => quotient.abs(),
+            false => quotient.abs().bitwise(&BigInt::from(1), |a, b| a | b),
+        };
+        let mut value = magnitude.to_f64();
+
+        // Scaled in steps, as 2^shift alone may not fit in a float even when the result does.
+        let mut shift = shift;
+        while shift != 0 {
+            let step = shift.clamp(-1000, 1000);
+            value /= 2f64.powi(step as i32);
+            shift -= step;
+        }
+        if quotient.is_negative() { -value } else { value }
+    }
+
+    pub fn add(&self, rhs: &Self) -> Self {
+        let numerator = self.numerator.mul(&rhs.denominator).add(&rhs.numerator.mul(&self.denominator));
+        Self::new(numerator, self.denominator.mul(&rhs.denominator)).expect("Denominators are never zero.")
+    }
+
+    pub fn sub(&self, rhs: &Self) -> Self {
+        self.add(&rhs.neg())
+    }
+
+    pub fn mul(&self, rhs: &Self) -> Self {
+        Self::new(self.numerator.mul(&rhs.numerator), self.denominator.mul(&rhs.denominator)).expect("Denominators are never zero.")
+    }
+
+    // `None` when dividing by zero.
+    pub fn div(&self, rhs: &Self) -> Option<Self> {
+        Self::new(self.numerator.mul(&rhs.denominator), self.denominator.mul(&rhs.numerator))
+    }
+
+    pub fn neg(&self) -> Self {
+        Self { numerator: self.numerator.neg(), denominator: self.denominator.clone() }
+    }
+
+    // Rounded toward negative infinity.
+    pub fn floor(&self) -> BigInt {
+        self.numerator.div_mod_floor(&self.denominator).expect("The denominator is never zero.").0
+    }
+
+    // Rounded toward positive infinity.
+    pub fn ceil(&self) -> BigInt {
+        self.neg().floor().neg()
+    }
+
+    // Rounded toward zero.
+    pub fn trunc(&self) -> BigInt {
+        self.numerator.div_rem(&self.denominator).expect("The denominator is never zero.").0
+    }
+
+    // Rounded to the nearest integer, halves away from zero.
+    pub fn round(&self) -> BigInt {
+        let half = Self { numerator: BigInt::from(1), denominator: BigInt::from(2) };
+        match self.numerator.is_negative() {
+            true => self.neg().add(&half).floor().neg(),
+            false => self.add(&half).floor(),
+        }
+    }
+
+    // `None` when raising zero to a negative power.
+    pub fn pow(&self, exponent: i64) -> Option<Self> {
+        let power = u32::try_from(exponent.unsigned_abs()).ok()?;
+        let (numerator, denominator) = (self.numerator.pow(power), self.denominator.pow(power));
+        match exponent < 0 {
+            true => Self::new(denominator, numerator),
+            false => Self::new(numerator, denominator),
+        }
+    }
+
+    pub fn pow_bits(&self, exponent: i64) -> u64 {
+        let power = u32::try_from(exponent.unsigned_abs()).unwrap_or(u32::MAX);
+        self.numerator.pow_bits(power).max(self.denominator.pow_bits(power))
+    }
+
+    // Rounded to `places` decimals, ties to even as banks do, and written out in full.
+    pub fn to_fixed(&self, places: u32) -> String {
+        let scale = BigInt::from(10).pow(places);
+        let (mut units, remainder) = self.numerator.mul(&scale).div_mod_floor(&self.denominator).expect("The denominator is never zero.");
+        match remainder.add(&remainder).cmp(&self.denominator) {
+            Ordering::Greater => units = units.add(&BigInt::from(1)),
+            Ordering::Equal if units.is_odd() => units = units.add(&BigInt::from(1)),
+            _ => {},
+        }
+
+        let digits = units.abs().to_string();
+        let digits = format!("{digits:0>width$}", width = places as usize + 1);
+        let (whole, fraction) = digits.split_at(digits.len() - places as usize);
+        let sign = if units.is_negative() { "-" } else { "" };
+        match places {
+            0 => format!("{sign}{whole}"),
+            _ => format!("{sign}{whole}.{fraction}"),
+        }
+    }
+}
+
+impl From<BigInt> for Rational {
+    fn from(value: BigInt) -> Self {
+        Self { numerator: value, denominator: BigInt::from(1) }
+    }
+}
+
+impl PartialOrd for Rational {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Rational {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.numerator.mul(&other.denominator).cmp(&other.numerator.mul(&self.denominator))
+    }
+}
+
+impl fmt::Display for Rational {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}/{}", self.numerator, self.denominator)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fraction(numerator: i64, denominator: i64) -> Rational {
+        Rational::new(BigInt::from(numerator), BigInt::from(denominator)).unwrap()
+    }
+
+    #[test]
+    fn lowest_terms_with_a_positive_denominator() {
+        assert_eq!(fraction(6, -4).to_string(), "-3/2");
+        assert_eq!(fraction(-6, -4).to_string(), "3/2");
+        assert_eq!(fraction(0, -5).to_string(), "0/1");
+        assert_eq!(fraction(7, 1).to_string(), "7/1");
+        assert_eq!(Rational::new(BigInt::from(1), BigInt::zero()), None);
+        assert_eq!(fraction(1, 3).add(&fraction(1, 6)), fraction(1, 2));
+        assert_eq!(fraction(1, 2).sub(&fraction(1, 2)), fraction(0, 1));
+        assert_eq!(fraction(-2, 3).mul(&fraction(3, 4)), fraction(-1, 2));
+        assert_eq!(fraction(1, 2).div(&fraction(-1, 4)), Some(fraction(-2, 1)));
+        assert_eq!(fraction(1, 2).div(&fraction(0, 1)), None);
+    }
+
+    #[test]
+    fn parse() {
+        assert_eq!(Rational::parse("-12.50"), Some(fraction(-25, 2)));
+        assert_eq!(Rational::parse("1/3"), Some(fraction(1, 3)));
+        assert_eq!(Rational::parse(" 2 / -4 "), Some(fraction(-1, 2)));
+        assert_eq!(Rational::parse("3."), Some(fraction(3, 1)));
+        for text in ["", "-", ".5", "1/0", "1.2.3", "a"] {
+            assert_eq!(Rational::parse(text), None, "{text:?}");
+        }
+    }
+
+    #[test]
+    fn rounding() {
+        let cases = [(fraction(5, 2), [2, 3, 2, 3]), (fraction(-5, 2), [-3, -2, -2, -3]), (fraction(-7, 3), [-3, -2, -2, -2])];
+        for (value, [floor, ceil, trunc, round]) in cases {
+            assert_eq!([value.floor(), value.ceil(), value.trunc(), value.round()], [floor, ceil, trunc, round].map(BigInt::from), "{value}");
+        }
+    }
+
+    #[test]
+    fn floats_round_trip() {
+        for value in [0.0, 0.5, -0.1, 1.0 / 3.0, 9007199254740992.0, 9007199254740993.0, 1e300, -f64::MAX, f64::MIN_POSITIVE, 5e-324] {
+            assert_eq!(Rational::from_f64(value).unwrap().to_f64(), value, "{value}");
+        }
+        assert_eq!(Rational::from_f64(0.75), Some(fraction(3, 4)));
+        assert_eq!(Rational::from_f64(f64::MAX).unwrap().numerator().bits(), 1024);
+        assert_eq!(Rational::from_f64(f64::INFINITY), None);
+        assert_eq!(Rational::from_f64(f64::NAN), None);
+    }
+
+    #[test]
+    fn to_f64_at_the_edges() {
+        assert_eq!(fraction(1, 3).to_f64(), 1.0 / 3.0);
+        assert_eq!(fraction(-2, 3).to_f64(), -2.0 / 3.0);
+        assert_eq!(Rational::from(BigInt::from(1).shl(1024)).to_f64(), f64::INFINITY);
+        assert_eq!(Rational::new(BigInt::from(1), BigInt::from(1).shl(1024)).unwrap().to_f64(), 5.562684646268003e-309);
+
+        // Just past a tie between two floats, which a dropped remainder would round down to even.
+        let two_53 = BigInt::from(1 << 53);
+        let past_tie = Rational::new(two_53.add(&BigInt::from(1)).mul(&BigInt::from(3)).add(&BigInt::from(1)), BigInt::from(3)).unwrap();
+        assert_eq!(past_tie.to_f64(), 9007199254740994.0);
+        assert_eq!(past_tie.neg().to_f64(), -9007199254740994.0);
+    }
+
+    #[test]
+    fn to_fixed_rounds_ties_to_even() {
+        assert_eq!(fraction(1, 8).to_fixed(2), "0.12");
+        assert_eq!(fraction(3, 8).to_fixed(2), "0.38");
+        assert_eq!(fraction(-1, 8).to_fixed(2), "-0.12");
+        assert_eq!(fraction(-1, 200).to_fixed(2), "0.00");
+        assert_eq!(fraction(5, 2).to_fixed(0), "2");
+        assert_eq!(fraction(7, 2).to_fixed(0), "4");
+        assert_eq!(fraction(1, 3).to_fixed(5), "0.33333");
+        assert_eq!(fraction(-2, 3).to_fixed(3), "-0.667");
+        assert_eq!(fraction(123, 1).to_fixed(2), "123.00");
+    }
+}
@@ -6,7 +6,28 @@ use crate::{interpreter::Interpreter, expr::*, stmt::*, error::*, token::Token};
 pub struct Resolver<'a> {
     interpreter: &'a mut Interpreter,
     scopes: Vec<HashMap<String, bool>>,
-    had_error: bool
+    had_error: bool,
+    symbols: Vec<Symbol>,
+    declarations: Vec<HashMap<String, usize>>,  // index into `symbols`, one map per entry of `scopes`
+    globals: HashMap<String, usize>,
+    unresolved: Vec<Token>,                     // uses of globals that were not declared yet
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SymbolKind {
+    Variable,
+    Parameter,
+    Function,
+}
+
+// A declared name and every place that refers to it, for editor tooling.
+#[derive(Debug, Clone)]
+pub struct Symbol {
+    pub name: Token,
+    pub kind: SymbolKind,
+    pub detail: String,     // the declaration as written, like `fun add(a, b)`
+    pub global: bool,
+    pub references: Vec<Token>,
 }
 
 impl<'a> ExprVisitor for Resolver<'a> {
@@ -44,6 +65,13 @@ impl<'a> ExprVisitor for Resolver<'a> {
         self.resolve_expr(&expr.expression)
     }
 
+    fn visit_interpolation_expr(&mut self, expr: Rc<InterpolationExpr>) -> Result<Self::Output, LoxError> {
+        for part in &expr.parts {
+            self.resolve_expr(part)?;
+        }
+        Ok(())
+    }
+
     fn visit_literal_expr(&mut self, _expr: Rc<LiteralExpr>) -> Result<Self::Output, LoxError> {
         Ok(())
     }
@@ -71,6 +99,7 @@ impl<'a> StmtVisitor for Resolver<'a> {
 
     fn visit_var_stmt(&mut self, stmt: Rc<VarStmt>) -> Result<Self::Output, LoxError> {
         self.declare(&stmt.name);
+        self.record(&stmt.name, SymbolKind::Variable, format!("var {}", stmt.name.lexeme));
         if let Some(init) = &stmt.initializer {
             self.resolve_expr(init)?;
         }
@@ -80,6 +109,8 @@ impl<'a> StmtVisitor for Resolver<'a> {
     
     fn visit_function_stmt(&mut self, stmt: Rc<FunctionStmt>) -> Result<Self::Output, LoxError> {
         self.declare(&stmt.name);
+        let parameters: Vec<&str> = stmt.parameters.iter().map(|param| param.lexeme.as_str()).collect();
+        self.record(&stmt.name, SymbolKind::Function, format!("fun {}({})", stmt.name.lexeme, parameters.join(", ")));
         self.define(&stmt.name);    // This lets a function recursively refer to itself inside its own body.
 
         self.resolve_function(stmt)?;
@@ -119,7 +150,20 @@ impl<'a> StmtVisitor for Resolver<'a> {
 
 impl<'a> Resolver<'a> {
     pub fn new(interpreter: &'a mut Interpreter) -> Self {
-        Self { interpreter, scopes: Vec::new() ,had_error: false }
+        Self {
+            interpreter, scopes: Vec::new() ,had_error: false,
+            symbols: Vec::new(), declarations: Vec::new(), globals: HashMap::new(), unresolved: Vec::new(),
+        }
+    }
+
+    // Globals are late bound, so a use that comes before its declaration is matched by name at the end.
+    pub fn symbols(mut self) -> Vec<Symbol> {
+        for name in std::mem::take(&mut self.unresolved) {
+            if let Some(&index) = self.globals.get(&name.lexeme) {
+                self.symbols[index].references.push(name);
+            }
+        }
+        self.symbols
     }
 
     pub fn success(&self)-> bool {
@@ -143,10 +187,18 @@ impl<'a> Resolver<'a> {
 
     fn resolve_local(&mut self, expr: Expr, name: &Token) -> Result<(), LoxError> {
         for (i, scope) in self.scopes.iter().rev().enumerate() {
-            if let Some(_) = scope.get(&name.lexeme) {
+            if scope.contains_key(&name.lexeme) {
+                let declaration = &self.declarations[self.scopes.len() - 1 - i];
+                if let Some(&index) = declaration.get(&name.lexeme) {
+                    self.symbols[index].references.push(name.clone());
+                }
                 return self.interpreter.resolve(expr, i)
             } 
         }
+        match self.globals.get(&name.lexeme) {
+            Some(&index) => self.symbols[index].references.push(name.clone()),
+            None => self.unresolved.push(name.clone()),
+        }
         Ok(())
     }
 
@@ -154,6 +206,7 @@ impl<'a> Resolver<'a> {
         self.begin_scope();
         for param in function.parameters.iter() {
             self.declare(param);
+            self.record(param, SymbolKind::Parameter, format!("parameter {}", param.lexeme));
             self.define(param);
         }
         self.resolve(&function.body)?;
@@ -163,10 +216,22 @@ impl<'a> Resolver<'a> {
 
     fn begin_scope(&mut self) {
         self.scopes.push(HashMap::new());
+        self.declarations.push(HashMap::new());
     }
 
     fn end_scope(&mut self) {
         self.scopes.pop().unwrap();
+        self.declarations.pop();
+    }
+
+    fn record(&mut self, name: &Token, kind: SymbolKind, detail: String) {
+        let index = self.symbols.len();
+        let global = self.declarations.is_empty();
+        self.symbols.push(Symbol { name: name.clone(), kind, detail, global, references: Vec::new() });
+        match self.declarations.last_mut() {
+            Some(scope) => scope.insert(name.lexeme.to_owned(), index),
+            None => self.globals.insert(name.lexeme.to_owned(), index),
+        };
     }
 
     fn declare(&mut self, name: &Token) {
@@ -1,13 +1,29 @@
 use crate::error::LoxError;
 use crate::token::{Token, TokenType};
 use crate::object::Object;
+use crate::bigint::BigInt;
+use crate::unicode;
+
+// Comments never reach the parser, but tools such as the formatter need them back.
+#[derive(Debug, Clone)]
+pub struct Comment {
+    pub text: String,
+    pub line: usize,
+    pub token: usize,       // index of the token that follows the comment
+    pub trailing: bool,     // whether code precedes the comment on its first line
+}
+
 // todo: these are looked like an OOP theme code which do not even fit rust
 pub struct Scanner {
     source: Vec<char>,
     tokens: Vec<Token>,
+    comments: Vec<Comment>,
     start: usize,
     current: usize,
     line: usize,
+    line_start: usize,      // index of the first character of the current line
+    column: usize,          // column of `start`
+    interpolations: Vec<usize>,     // braces open inside each `${` being scanned, innermost last
 }
 
 impl Scanner {
@@ -15,9 +31,13 @@ impl Scanner {
         Self {
             source: source.chars().collect(), 
             tokens: vec![],
+            comments: vec![],
             start: 0,
             current: 0,
             line: 1,
+            line_start: 0,
+            column: 1,
+            interpolations: vec![],
         }
     }
     
@@ -25,11 +45,16 @@ impl Scanner {
         let mut had_error: Option<LoxError> = None;
         while !self.is_at_end() {
             self.start = self.current;
+            self.column = self.current - self.line_start + 1;
             if let Err(e) = self.scan_token() {
                 had_error = Some(e);
             }
         }
         self.start = self.current;
+        self.column = self.current - self.line_start + 1;
+        if !self.interpolations.is_empty() {
+            had_error = Some(LoxError::scan_error(self.line, "Unterminated string interpolation."));
+        }
         self.add_token(TokenType::Eof); 
 
         match had_error {
@@ -38,19 +63,53 @@ impl Scanner {
         }
     }
 
+    pub fn comments(&self) -> &[Comment] {
+        &self.comments
+    }
+
     fn scan_token(&mut self) -> Result<(), LoxError> {
         let c = self.advance();
         match c {
             '(' => self.add_token(TokenType::LeftParen),
             ')' => self.add_token(TokenType::RightParen),
-            '{' => self.add_token(TokenType::LeftBrace),
-            '}' => self.add_token(TokenType::RightBrace),
+            '{' => {
+                if let Some(depth) = self.interpolations.last_mut() {
+                    *depth += 1;
+                }
+                self.add_token(TokenType::LeftBrace);
+            },
+            '}' => match self.interpolations.last_mut() {
+                // This closes a `${`, and the string it interrupted goes on.
+                Some(0) => {
+                    self.interpolations.pop();
+                    self.string(true)?;
+                },
+                Some(depth) => {
+                    *depth -= 1;
+                    self.add_token(TokenType::RightBrace);
+                },
+                None => self.add_token(TokenType::RightBrace),
+            },
             ',' => self.add_token(TokenType::Comma),
-            '.' => self.add_token(TokenType::Dot),
+            '.' => match self.peek(0) {
+                Some('0'..='9') => self.number()?,
+                _ => self.add_token(TokenType::Dot),
+            },
             '-' => self.add_token(TokenType::Minus),
             '+' => self.add_token(TokenType::Plus),
             ';' => self.add_token(TokenType::SemiColon),
-            '*' => self.add_token(TokenType::Star),
+            '*' => {
+                let tok = match self.is_match('*') {true => TokenType::StarStar, false => TokenType::Star};
+                self.add_token(tok);
+            },
+            '%' => self.add_token(TokenType::Percent),
+            '~' => {
+                let tok = match self.is_match('/') {true => TokenType::TildeSlash, false => TokenType::Tilde};
+                self.add_token(tok);
+            },
+            '&' => self.add_token(TokenType::Ampersand),
+            '|' => self.add_token(TokenType::Pipe),
+            '^' => self.add_token(TokenType::Caret),
             '!' => {
                 let tok = match self.is_match('=') {true => TokenType::BangEqual, false => TokenType::Bang };
                 self.add_token(tok);
@@ -60,30 +119,60 @@ impl Scanner {
                 self.add_token(tok);
             },
             '<' => {
-                let tok = match self.is_match('=') {true => TokenType::LessEqual, false => TokenType::Less};
+                let tok = if self.is_match('=') {
+                    TokenType::LessEqual
+                } else if self.is_match('<') {
+                    TokenType::LessLess
+                } else {
+                    TokenType::Less
+                };
                 self.add_token(tok);
             },
             '>' => {
-                let tok = match self.is_match('=') {true => TokenType::GreaterEqual, false => TokenType::Greater};
+                let tok = if self.is_match('=') {
+                    TokenType::GreaterEqual
+                } else if self.is_match('>') {
+                    TokenType::GreaterGreater
+                } else {
+                    TokenType::Greater
+                };
                 self.add_token(tok);
             },
             '/' => match self.peek(0) {
-                Some('/') => while let Some(ch) = self.peek(0) { match ch {
-                    '\n' => break,
-                    _ => self.advance(),
-                };},
+                Some('/') => {
+                    while let Some(ch) = self.peek(0) { match ch {
+                        '\n' => break,
+                        _ => self.advance(),
+                    };}
+                    self.add_comment(self.line);
+                },
                 Some('*') => {
+                    let line = self.line;
                     self.advance();
-                    self.comment()?
+                    self.comment()?;
+                    self.add_comment(line);
                 },
                 _ => self.add_token(TokenType::Slash),
             },
-            '"' => self.string()?,
-            '0'..='9' => self.number(),
-            'a'..='z' | 'A'..='Z' | '_' => self.indentifier(),
+            '"' if self.peek(0) == Some('"') && self.peek(1) == Some('"') => {
+                self.current += 2;
+                self.block_string(false)?;
+            },
+            '"' => self.string(false)?,
+            'r' if self.peek(0) == Some('"') => {
+                self.advance();
+                if self.peek(0) == Some('"') && self.peek(1) == Some('"') {
+                    self.current += 2;
+                    self.block_string(true)?;
+                } else {
+                    self.raw_string()?;
+                }
+            },
+            '0'..='9' => self.number()?,
+            ch if unicode::is_xid_start(ch) => self.indentifier(),
             ' ' | '\r' | '\t' => (),
-            '\n' => self.line += 1,
-            ch => return Err(LoxError::scan_error(self.line, &format!("Unexpected Charactor: '{}'", ch))),
+            '\n' => self.new_line(self.current),
+            ch => return Err(LoxError::scan_error_at(self.line, self.column, &format!("Unexpected Charactor: '{}'", ch))),
         }
         Ok(())
     }
@@ -94,7 +183,7 @@ impl Scanner {
             match (ch1, ch2) {
                 ('/', '*') => {self.advance(); self.comment()? },
                 ('*', '/') => {self.advance(); return Ok(())},
-                (_, '\n') => self.line += 1, 
+                (_, '\n') => self.new_line(self.current + 1), 
                 _ => {}, 
             }
         }
@@ -102,7 +191,7 @@ impl Scanner {
     } 
 
     fn indentifier(&mut self) {
-        while let Some('a'..='z' | 'A'..='Z' | '0'..='9' | '_') = self.peek(0) {
+        while self.peek(0).is_some_and(unicode::is_xid_continue) {
             self.advance();
         }
 
@@ -110,26 +199,126 @@ impl Scanner {
         self.add_token(Scanner::keywords(&value));
     }
 
-    fn number(&mut self) { // what a nice code!
-        while let Some('0'..='9') = self.peek(0) { self.advance();}        
+    // `0x`, `0b` and `0o` literals and decimals without a fraction or an exponent are integers,
+    // everything else is a float. `_` may separate digits anywhere in a literal.
+    fn number(&mut self) -> Result<(), LoxError> { // what a nice code!
+        self.current = self.start;
+        let radix = match (self.peek(0), self.peek(1)) {
+            (Some('0'), Some('x' | 'X')) => Some((16, "hexadecimal")),
+            (Some('0'), Some('o' | 'O')) => Some((8, "octal")),
+            (Some('0'), Some('b' | 'B')) => Some((2, "binary")),
+            _ => None,
+        };
 
-        if let (Some('.'), Some('0'..='9')) = (self.peek(0), self.peek(1)) { 
+        if let Some((radix, kind)) = radix {
+            self.current += 2;
+            let digits = self.digits(radix)?;
+            self.check_number_end(|ch, lexeme| format!("Invalid digit '{ch}' in {kind} literal '{lexeme}'."))?;
+            let value = BigInt::parse_radix(&digits, radix)
+                .ok_or_else(|| LoxError::scan_error(self.line, &format!("Expect digits after '{}' in number literal.", self.lexeme())))?;
+            self.add_token_object(TokenType::Number, Some(Object::integer(value)));
+            return Ok(());
+        }
+
+        let mut text = self.digits(10)?;
+        let mut float = false;
+        if let (Some('.'), Some('0'..='9')) = (self.peek(0), self.peek(1)) {
             self.advance();
-            while let Some('0'..='9') = self.peek(0) { self.advance();}        
+            text = format!("{text}.{}", self.digits(10)?);
+            float = true;
         }
-        
-        let value: String = self.source[self.start..self.current].iter().collect();
-        let num: f64 = value.parse().unwrap();                       
-        self.add_token_object(TokenType::Number, Some(Object::Num(num)));
+        if let Some('e' | 'E') = self.peek(0) {
+            self.advance();
+            let sign = match self.peek(0) {
+                Some(sign @ ('+' | '-')) => { self.advance(); sign.to_string() },
+                _ => String::new(),
+            };
+            let exponent = self.digits(10)?;
+            if exponent.is_empty() {
+                return Err(LoxError::scan_error(self.line, &format!("Expect digits in the exponent of number literal '{}'.", self.lexeme())));
+            }
+            text = format!("{text}e{sign}{exponent}");
+            float = true;
+        }
+        self.check_number_end(|ch, lexeme| format!("Invalid character '{ch}' in number literal '{lexeme}'."))?;
+
+        let object = match float {
+            true => match text.parse::<f64>() {
+                Ok(value) if value.is_finite() => Object::Num(value),
+                _ => return Err(LoxError::scan_error(self.line, &format!("Number literal '{}' is too large.", self.lexeme()))),
+            },
+            false => Object::integer(BigInt::parse(&text).expect("Only digits were scanned.")),
+        };
+        self.add_token_object(TokenType::Number, Some(object));
+        Ok(())
     }
 
-    fn string(&mut self) -> Result<(), LoxError> {
+    // A run of digits in `radix` with the `_` separators taken out; they may only sit between two digits.
+    fn digits(&mut self, radix: u32) -> Result<String, LoxError> {
+        let mut digits = String::new();
+        let mut separated = false;
+        while let Some(ch) = self.peek(0) {
+            match ch {
+                '_' if digits.is_empty() || separated => {
+                    self.skip_word();
+                    return Err(LoxError::scan_error(self.line, &format!("'_' must separate digits in number literal '{}'.", self.lexeme())));
+                },
+                '_' => separated = true,
+                ch if ch.is_digit(radix) => {
+                    separated = false;
+                    digits.push(ch);
+                },
+                _ => break,
+            }
+            self.advance();
+        }
+        match separated {
+            true => Err(LoxError::scan_error(self.line, &format!("'_' must separate digits in number literal '{}'.", self.lexeme()))),
+            false => Ok(digits),
+        }
+    }
+
+    // A literal must not run into letters or digits it cannot hold; the whole run is reported.
+    fn check_number_end(&mut self, message: impl Fn(char, &str) -> String) -> Result<(), LoxError> {
+        let Some(ch) = self.peek(0).filter(|ch| unicode::is_xid_continue(*ch)) else {
+            return Ok(());
+        };
+        self.skip_word();
+        Err(LoxError::scan_error(self.line, &message(ch, &self.lexeme())))
+    }
+
+    // After a malformed literal, so the rest of it is neither scanned again nor left out of the message.
+    fn skip_word(&mut self) {
+        while self.peek(0).is_some_and(unicode::is_xid_continue) { self.advance(); }
+    }
+
+    fn lexeme(&self) -> String {
+        self.source[self.start..self.current].iter().collect()
+    }
+
+    // The token keeps the string as written and its literal the value with escapes replaced.
+    // Every bad escape is reported, and the string is still scanned to its end.
+    // A `${` ends the token early: what follows is scanned as code up to the matching `}`,
+    // where scanning the string resumes.
+    fn string(&mut self, resumed: bool) -> Result<(), LoxError> {
+        let mut value = String::new();
+        let mut error = None;
         while let Some(ch) = self.peek(0) { 
             match ch {
             '"' => break,
-            '\n' => self.line += 1,
+            '$' if self.peek(1) == Some('{') => break,
+            '\n' => self.new_line(self.current + 1),
+            '\\' => {
+                match self.escape() {
+                    Ok(Some(ch)) => value.push(ch),
+                    Ok(None) => break,
+                    Err(e) => error = Some(e),
+                }
+                continue;
+            },
             _ => {},
             }
+            value.push(ch);
             self.advance();
         }
 
@@ -137,13 +326,176 @@ impl Scanner {
             return Err(LoxError::scan_error(self.line, "Unterminated String."));
         }
 
-        self.advance(); // advance after check
+        let ttype = match self.advance() {
+            '$' => {
+                self.advance();
+                self.interpolations.push(0);
+                TokenType::Interpolation
+            },
+            _ if resumed => TokenType::InterpolationEnd,
+            _ => TokenType::String,
+        };
         
-        let value: String = self.source[self.start + 1 .. self.current - 1].iter().collect();
+        match error {
+            Some(e) => Err(e),
+            None => {
+                self.add_token_object(ttype, Some(Object::Str(value)));
+                Ok(())
+            },
+        }
+    }
+
+    // `r"C:\path"`: everything up to the next `"` as written, backslashes and `${` included.
+    fn raw_string(&mut self) -> Result<(), LoxError> {
+        let mut value = String::new();
+        while let Some(ch) = self.peek(0) {
+            match ch {
+                '"' => break,
+                '\n' => self.new_line(self.current + 1),
+                _ => {},
+            }
+            value.push(ch);
+            self.advance();
+        }
+
+        if self.is_at_end() {
+            return Err(LoxError::scan_error(self.line, "Unterminated String."));
+        }
+        self.advance();
         self.add_token_object(TokenType::String, Some(Object::Str(value)));
         Ok(())
     }
 
+    // `"""` strings, raw or not, leave out a blank first line, a blank last line and the
+    // indentation shared by the other non-blank lines, and always break lines with `\n`.
+    // They do not interpolate. The whole string is found first, as every line decides the indentation.
+    fn block_string(&mut self, raw: bool) -> Result<(), LoxError> {
+        let content = self.current;
+        let mut end = content;
+        while !matches!(self.source.get(end..end + 3), Some(['"', '"', '"'])) {
+            match self.source.get(end) {
+                Some('\\') if !raw => end += 2,
+                Some(_) => end += 1,
+                None => {
+                    while let Some(ch) = self.peek(0) {
+                        if ch == '\n' {
+                            self.new_line(self.current + 1);
+                        }
+                        self.advance();
+                    }
+                    return Err(LoxError::scan_error(self.line, "Unterminated String."));
+                },
+            }
+        }
+        let end = end.min(self.source.len());
+
+        let blank = |line: &[char]| line.iter().all(|ch| matches!(ch, ' ' | '\t' | '\r'));
+        let lines: Vec<&[char]> = self.source[content..end].split(|ch| *ch == '\n').collect();
+        let indent = lines.iter().skip(1)
+            .filter(|line| !blank(line))
+            .map(|line| line.iter().take_while(|ch| matches!(ch, ' ' | '\t')).count())
+            .min()
+            .unwrap_or(0);
+        // What is kept lies between `first` and `last`, which leave out the blank lines with their line breaks.
+        let (mut first, mut last) = (content, end);
+        if lines.len() > 1 {
+            if blank(lines[0]) {
+                first += lines[0].len() + 1;
+            }
+            if blank(lines[lines.len() - 1]) {
+                last -= lines[lines.len() - 1].len() + 1;
+            }
+        }
+
+        let mut value = String::new();
+        let mut error = None;
+        let mut dedent = 0;     // indentation still to leave out on this line
+        while self.current < end {
+            let ch = self.source[self.current];
+            let kept = (first..last).contains(&self.current);
+            match ch {
+                '\n' => {
+                    self.new_line(self.current + 1);
+                    dedent = indent;
+                },
+                ' ' | '\t' if dedent > 0 => {
+                    dedent -= 1;
+                    self.advance();
+                    continue;
+                },
+                '\\' if kept && !raw => {
+                    dedent = 0;
+                    match self.escape() {
+                        Ok(Some(ch)) => value.push(ch),
+                        Ok(None) => {},
+                        Err(e) => error = Some(e),
+                    }
+                    continue;
+                },
+                _ => dedent = 0,
+            }
+            if kept && !(ch == '\r' && self.peek(1) == Some('\n')) {
+                value.push(ch);
+            }
+            self.advance();
+        }
+        self.current = end + 3;
+
+        match error {
+            Some(e) => Err(e),
+            None => {
+                self.add_token_object(TokenType::String, Some(Object::Str(value)));
+                Ok(())
+            },
+        }
+    }
+
+    // Reads the escape sequence at the current backslash; `None` when the source ends inside it.
+    fn escape(&mut self) -> Result<Option<char>, LoxError> {
+        let column = self.current - self.line_start + 1;
+        self.advance();
+        let Some(ch) = self.peek(0) else { return Ok(None) };
+        self.advance();
+        let escaped = match ch {
+            'n' => '\n',
+            't' => '\t',
+            'r' => '\r',
+            '0' => '\0',
+            '"' => '"',
+            '\\' => '\\',
+            '$' => '$',
+            'u' => return self.unicode_escape(column).map(Some),
+            '\n' => {
+                self.new_line(self.current);
+                return Err(LoxError::scan_error_at(self.line - 1, column, "Unknown escape sequence '\\' at end of line."));
+            },
+            ch => return Err(LoxError::scan_error_at(self.line, column, &format!("Unknown escape sequence '\\{ch}'."))),
+        };
+        Ok(Some(escaped))
+    }
+
+    // `\u{1F600}`: one to six hexadecimal digits naming a Unicode scalar value.
+    fn unicode_escape(&mut self, column: usize) -> Result<char, LoxError> {
+        let invalid = |scanner: &Self| {
+            let escape: String = scanner.source[scanner.line_start + column - 1..scanner.current].iter().collect();
+            LoxError::scan_error_at(scanner.line, column, &format!("Invalid Unicode escape '{escape}', expected '\\u{{' with 1 to 6 hexadecimal digits and '}}'."))
+        };
+        if !self.is_match('{') {
+            return Err(invalid(self));
+        }
+        let mut digits = String::new();
+        while let Some(ch) = self.peek(0).filter(char::is_ascii_hexdigit) {
+            digits.push(ch);
+            self.advance();
+        }
+        let closed = self.is_match('}');
+        if digits.is_empty() || digits.len() > 6 || !closed {
+            return Err(invalid(self));
+        }
+        let code = u32::from_str_radix(&digits, 16).expect("Only hexadecimal digits were read.");
+        char::from_u32(code).ok_or_else(|| LoxError::scan_error_at(self.line, column, &format!("'\\u{{{digits}}}' is not a Unicode scalar value.")))
+    }
+
     fn is_match(&mut self, expected: char) -> bool {
         match self.source.get(self.current) {
             Some(ch) if *ch == expected => {
@@ -196,9 +548,22 @@ impl Scanner {
         self.add_token_object(ttype, None);
     }
 
+    fn new_line(&mut self, line_start: usize) {
+        self.line += 1;
+        self.line_start = line_start;
+    }
+
+    fn add_comment(&mut self, line: usize) {
+        let text: String = self.source[self.start..self.current].iter().collect();
+        let trailing = matches!(self.tokens.last(), Some(token) if token.line == line)
+            || matches!(self.comments.last(), Some(comment) if comment.line == line);
+        let text = text.strip_suffix('\r').unwrap_or(&text).to_owned();      // as written, but for a `\r\n` line break
+        self.comments.push(Comment { text, line, token: self.tokens.len(), trailing });
+    }
+
     fn add_token_object(&mut self, ttype: TokenType, literal: Option<Object>) {
         let s: String = self.source[self.start..self.current].iter().collect(); // convert a [char] to String
-        self.tokens.push(Token::new(ttype, s, literal, self.line));
+        self.tokens.push(Token::new(ttype, s, literal, self.line, self.column));
     }
 }
 
@@ -17,6 +17,7 @@ pub trait StmtVisitor {
     fn visit_while_stmt(&mut self, stmt: Rc<WhileStmt>) -> Result<Self::Output, LoxError>;
 }
 
+#[derive(Clone)]
 pub enum Stmt {
     Break(Rc<BreakStmt>),
     Block(Rc<BlockStmt>),
@@ -54,6 +55,7 @@ pub struct IfStmt {
 }
 
 pub struct PrintStmt {
+    pub keyword: Token,
     pub expression: Expr,
 }
 
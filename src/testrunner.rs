@@ -0,0 +1,144 @@
+use std::{fs, io, path::{Path, PathBuf}, process::Command};
+
+// Runs every `.lox` file under a directory and checks it against the annotations
+// used by the Crafting Interpreters test suite:
+//
+//     print 1;                    // expect: 1
+//     print nil + 1;              // expect runtime error: Unexpected Type of operands for operator '+'.
+//     var 1 = 2;                  // Error at '1': Expect Variable name.
+//     // [line 3] Error at end: Expect '}' after block.
+//
+// Each script is run in a fresh `rlox` process so exit codes and stderr can be checked too.
+pub struct TestRunner {
+    interpreter: PathBuf,
+    passed: usize,
+    failed: usize,
+}
+
+struct Expectations {
+    output: Vec<String>,
+    compile_errors: Vec<String>,
+    runtime_error: Option<String>,
+}
+
+impl Expectations {
+    fn parse(source: &str) -> Self {
+        let mut expect = Self { output: Vec::new(), compile_errors: Vec::new(), runtime_error: None };
+
+        for (i, line) in source.lines().enumerate() {
+            let line_number = i + 1;
+            if let Some((_, output)) = line.split_once("// expect: ") {
+                expect.output.push(output.to_owned());
+            } else if let Some((_, message)) = line.split_once("// expect runtime error: ") {
+                expect.runtime_error = Some(format!("[line {line_number}] Error: {message}"));
+            } else if let Some((_, error)) = line.split_once("// [line ") {
+                expect.compile_errors.push(format!("[line {error}"));
+            } else if let Some(error) = Self::compile_error(line) {
+                expect.compile_errors.push(format!("[line {line_number}] Error{error}"));
+            }
+        }
+        expect
+    }
+
+    // What follows "Error" in `// Error at 'x': ...` or `// Error: ...`. Other comments that happen
+    // to start with "Error", such as `// Errors are reported below`, expect nothing.
+    fn compile_error(line: &str) -> Option<&str> {
+        ["// Error at ", "// Error: "].into_iter().find_map(|marker| {
+            let (before, _) = line.split_once(marker)?;
+            Some(&line[before.len() + "// Error".len()..])
+        })
+    }
+
+    fn exit_code(&self) -> i32 {
+        if !self.compile_errors.is_empty() {
+            65
+        } else if self.runtime_error.is_some() {
+            70
+        } else {
+            0
+        }
+    }
+}
+
+impl TestRunner {
+    pub fn new(interpreter: PathBuf) -> Self {
+        Self { interpreter, passed: 0, failed: 0 }
+    }
+
+    pub fn success(&self) -> bool {
+        self.failed == 0
+    }
+
+    pub fn summary(&self) {
+        println!("Passed {} tests, failed {}.", self.passed, self.failed);
+    }
+
+    pub fn run_dir(&mut self, dir: &Path) -> io::Result<()> {
+        let mut entries = fs::read_dir(dir)?
+            .map(|entry| entry.map(|e| e.path()))
+            .collect::<io::Result<Vec<_>>>()?;
+        entries.sort();
+
+        for path in entries {
+            if path.is_dir() {
+                self.run_dir(&path)?;
+            } else if path.extension().is_some_and(|ext| ext == "lox") {
+                self.run_file(&path)?;
+            }
+        }
+        Ok(())
+    }
+
+    fn run_file(&mut self, path: &Path) -> io::Result<()> {
+        let expect = Expectations::parse(&fs::read_to_string(path)?);
+        let output = Command::new(&self.interpreter).arg(path).output()?;
+
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        let mut failures = Vec::new();
+
+        let exit_code = output.status.code().unwrap_or(-1);
+        if exit_code != expect.exit_code() {
+            failures.push(format!("Expected exit code {} but got {}.", expect.exit_code(), exit_code));
+        }
+
+        let errors: Vec<&str> = stderr.lines().collect();
+        if let Some(runtime_error) = &expect.runtime_error {
+            if !errors.contains(&runtime_error.as_str()) {
+                failures.push(format!("Missing expected runtime error '{runtime_error}'."));
+            }
+        } else {
+            for error in &expect.compile_errors {
+                if !errors.contains(&error.as_str()) {
+                    failures.push(format!("Missing expected error '{error}'."));
+                }
+            }
+            for error in errors.iter().filter(|e| !expect.compile_errors.iter().any(|x| x == *e)) {
+                failures.push(format!("Unexpected error '{error}'."));
+            }
+        }
+
+        let lines: Vec<&str> = stdout.lines().collect();
+        for (i, expected) in expect.output.iter().enumerate() {
+            match lines.get(i) {
+                Some(line) if line == expected => {},
+                Some(line) => failures.push(format!("Expected output '{expected}' on line {} but got '{line}'.", i + 1)),
+                None => failures.push(format!("Missing expected output '{expected}'.")),
+            }
+        }
+        for line in lines.iter().skip(expect.output.len()) {
+            failures.push(format!("Got output '{line}' when none was expected."));
+        }
+
+        if failures.is_empty() {
+            self.passed += 1;
+        } else {
+            self.failed += 1;
+            println!("FAIL {}", path.display());
+            for failure in failures {
+                println!("     {failure}");
+            }
+        }
+        Ok(())
+    }
+}
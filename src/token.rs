@@ -14,6 +14,15 @@ pub enum TokenType {
     SemiColon,
     Slash,
     Star,
+    StarStar,
+    Percent,
+    TildeSlash,
+    Ampersand,
+    Pipe,
+    Caret,
+    Tilde,
+    LessLess,
+    GreaterGreater,
     Bang,
     BangEqual,
     Assign,
@@ -24,6 +33,8 @@ pub enum TokenType {
     LessEqual,
     Identifier,
     String,
+    Interpolation,      // a string up to a `${`, or from one `}` to the next `${`
+    InterpolationEnd,   // the rest of an interpolated string after its last `}`
     Number,
     And,
     Class,
@@ -55,11 +66,12 @@ pub struct Token {
     pub lexeme: String, 
     pub literal: Option<Object>,
     pub line: usize,
+    pub column: usize,      // column of the first character, counted in characters from 1
 }
 
 impl Token {
-    pub fn new(ttype: TokenType, lexeme: String, literal: Option<Object>, line: usize) -> Token {
-        Token {ttype, lexeme, literal, line}
+    pub fn new(ttype: TokenType, lexeme: String, literal: Option<Object>, line: usize, column: usize) -> Token {
+        Token {ttype, lexeme, literal, line, column}
     }
 }
 
@@ -0,0 +1,79 @@
+use std::{cell::RefCell, collections::HashMap, io::{self, Write}, rc::Rc};
+
+use crate::{stmt::Stmt, object::Object, token::Token, error::LoxError, environment::Environment,
+    callable::LoxCallable, hook::ExecutionHook};
+
+// Logs every statement, call, return and assignment as it happens, indented by call depth.
+// With `only` set, just what happens inside calls to those functions is logged.
+pub struct Tracer {
+    lines: HashMap<Stmt, usize>,
+    source: Vec<String>,
+    only: Vec<String>,
+    out: Box<dyn Write>,
+    calls: Vec<(String, bool)>,     // name, and whether it is one of `only`
+    active: usize,                  // calls to `only` functions in progress
+    line: usize,                    // of the last statement
+}
+
+impl ExecutionHook for Tracer {
+    fn statement(&mut self, stmt: &Stmt, _environment: &Rc<RefCell<Environment>>) -> Result<(), LoxError> {
+        if matches!(stmt, Stmt::Block(_)) {
+            return Ok(());
+        }
+        if let Some(&line) = self.lines.get(stmt) {
+            self.line = line;
+            let text = self.source.get(line - 1).map_or("", |text| text.trim()).to_owned();
+            self.log(self.calls.len(), line, &text);
+        }
+        Ok(())
+    }
+
+    fn enter(&mut self, function: &dyn LoxCallable, paren: &Token, arguments: &[Object]) {
+        let name = function.name().to_owned();
+        let selected = self.only.contains(&name);
+        self.active += usize::from(selected);
+
+        let arguments: Vec<String> = arguments.iter().map(|argument| argument.to_string()).collect();
+        self.log(self.calls.len(), paren.line, &format!("call {name}({})", arguments.join(", ")));
+        self.calls.push((name, selected));
+    }
+
+    fn leave(&mut self, result: &Result<Object, LoxError>) {
+        let Some((name, _)) = self.calls.last() else { return };
+        let text = match result {
+            Ok(value) => format!("return {name} -> {value}"),
+            Err(_) => format!("error in {name}"),
+        };
+        self.log(self.calls.len() - 1, self.line, &text);
+
+        if let Some((_, selected)) = self.calls.pop() {
+            self.active -= usize::from(selected);
+        }
+    }
+
+    fn assign(&mut self, name: &Token, value: &Object, depth: Option<usize>) {
+        let depth = match depth {
+            Some(depth) => format!("depth {depth}"),
+            None => "global".to_owned(),
+        };
+        self.log(self.calls.len(), name.line, &format!("assign {} = {value} ({depth})", name.lexeme));
+    }
+
+    fn finish(&mut self) -> io::Result<()> {
+        self.out.flush()
+    }
+}
+
+impl Tracer {
+    pub fn new(lines: HashMap<Stmt, usize>, source: &str, only: Vec<String>, out: Box<dyn Write>) -> Self {
+        let source = source.lines().map(str::to_owned).collect();
+        Self { lines, source, only, out, calls: Vec::new(), active: 0, line: 0 }
+    }
+
+    // A failed write cannot stop the program being traced, so it is dropped.
+    fn log(&mut self, depth: usize, line: usize, text: &str) {
+        if self.only.is_empty() || self.active > 0 {
+            let _ = writeln!(self.out, "{:indent$}[line {line}] {text}", "", indent = depth * 2);
+        }
+    }
+}
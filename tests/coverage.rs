@@ -0,0 +1,78 @@
+use std::process::Command;
+
+const SCRIPT: &str = "fun square(n) {
+  return n * n;
+}
+fun unused() {
+  print \"never\";
+}
+var i = 0;
+while (i < 3) {
+  if (i == 1) print square(i); else print i;
+  i = i + 1;
+}
+if (i > 5) print \"big\";
+";
+
+// `--coverage` annotates the source with hits and branch counts, and writes the same as lcov.
+#[test]
+fn report() {
+    let dir = std::env::temp_dir().join(format!("rlox-coverage-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    std::fs::write(dir.join("script.lox"), SCRIPT).unwrap();
+    let output = Command::new(env!("CARGO_BIN_EXE_rlox")).arg("--coverage=out.info").arg("script.lox").current_dir(&dir).output().unwrap();
+    let lcov = std::fs::read_to_string(dir.join("out.info")).unwrap();
+    let script = dir.join("script.lox").canonicalize().unwrap();
+    std::fs::remove_dir_all(&dir).unwrap();
+
+    assert!(output.status.success());
+    assert_eq!(String::from_utf8(output.stdout).unwrap(), "0\n1\n2\n");
+    let summary = String::from_utf8(output.stderr).unwrap();
+    assert_eq!(summary, "        1 | fun square(n) {
+        1 |   return n * n;
+          | }
+        1 | fun unused() {
+    ##### |   print \"never\";
+          | }
+        1 | var i = 0;
+        1 | while (i < 3) {    [3/1]
+        3 |   if (i == 1) print square(i); else print i;    [1/2]
+        3 |   i = i + 1;
+          | }
+        1 | if (i > 5) print \"big\";    [0/1]
+
+Lines: 8/9 (88.9%)
+Branches: 5/6 (83.3%)
+Functions: 1/2 (50.0%)
+");
+    assert_eq!(lcov, format!("\
+TN:
+SF:{}
+FN:1,square
+FN:4,unused
+FNDA:1,square
+FNDA:0,unused
+FNF:2
+FNH:1
+BRDA:8,0,0,3
+BRDA:8,0,1,1
+BRDA:9,0,0,1
+BRDA:9,0,1,2
+BRDA:12,0,0,0
+BRDA:12,0,1,1
+BRF:6
+BRH:5
+DA:1,1
+DA:2,1
+DA:4,1
+DA:5,0
+DA:7,1
+DA:8,1
+DA:9,3
+DA:10,3
+DA:12,1
+LF:9
+LH:8
+end_of_record
+", script.display()));
+}
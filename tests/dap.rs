@@ -0,0 +1,150 @@
+use std::io::{BufRead, BufReader, Read, Write};
+use std::process::{Child, ChildStdin, ChildStdout, Command, Stdio};
+
+const SCRIPT: &str = "fun square(n) {
+  var result = n * n;
+  return result;
+}
+print square(3);
+eprint(\"done\");
+";
+
+fn send(stdin: &mut ChildStdin, body: &str) {
+    write!(stdin, "Content-Length: {}\r\n\r\n{body}", body.len()).unwrap();
+    stdin.flush().unwrap();
+}
+
+// Reads messages until one contains `needle`, and returns that one.
+fn until(stdout: &mut BufReader<ChildStdout>, needle: &str) -> String {
+    loop {
+        let mut length = None;
+        loop {
+            let mut header = String::new();
+            assert_ne!(stdout.read_line(&mut header).unwrap(), 0, "no message with {needle}");
+            match header.trim_end().split_once(": ") {
+                Some(("Content-Length", value)) => length = value.parse().ok(),
+                _ if header.trim_end().is_empty() => break,
+                _ => {},
+            }
+        }
+        let mut body = vec![0; length.unwrap()];
+        stdout.read_exact(&mut body).unwrap();
+        let body = String::from_utf8(body).unwrap();
+        if body.contains(needle) {
+            return body;
+        }
+    }
+}
+
+fn adapter(args: &[&str]) -> (Child, ChildStdin, BufReader<ChildStdout>) {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_rlox"))
+        .args(args)
+        .arg("dap")
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()
+        .unwrap();
+    let stdin = child.stdin.take().unwrap();
+    let stdout = BufReader::new(child.stdout.take().unwrap());
+    (child, stdin, stdout)
+}
+
+// Drives `rlox dap` through a breakpoint, waiting for each reply as an editor would.
+#[test]
+fn session() {
+    let path = std::env::temp_dir().join(format!("rlox-dap-{}.lox", std::process::id()));
+    std::fs::write(&path, SCRIPT).unwrap();
+    let program = path.canonicalize().unwrap().display().to_string();
+
+    let (mut child, mut stdin, mut stdout) = adapter(&[]);
+
+    send(&mut stdin, r#"{"seq":1,"type":"request","command":"initialize","arguments":{}}"#);
+    until(&mut stdout, r#""event":"initialized""#);
+    send(&mut stdin, &format!(r#"{{"seq":2,"type":"request","command":"launch","arguments":{{"program":"{program}"}}}}"#));
+    let breakpoints = format!(r#"{{"seq":3,"type":"request","command":"setBreakpoints","arguments":{{"source":{{"path":"{program}"}},"breakpoints":[{{"line":3}}]}}}}"#);
+    send(&mut stdin, &breakpoints);
+    let reply = until(&mut stdout, r#""request_seq":3"#);
+    assert!(reply.contains(r#""breakpoints":[{"verified":true,"line":3}]"#), "{reply}");
+    send(&mut stdin, r#"{"seq":4,"type":"request","command":"configurationDone"}"#);
+
+    let stopped = until(&mut stdout, r#""event":"stopped""#);
+    assert!(stopped.contains(r#""reason":"breakpoint""#), "{stopped}");
+    send(&mut stdin, r#"{"seq":5,"type":"request","command":"stackTrace","arguments":{"threadId":1}}"#);
+    let reply = until(&mut stdout, r#""request_seq":5"#);
+    assert!(reply.contains(r#""totalFrames":2"#) && reply.contains(r#""name":"square","#) && reply.contains(r#""line":3,"#), "{reply}");
+    send(&mut stdin, r#"{"seq":6,"type":"request","command":"scopes","arguments":{"frameId":0}}"#);
+    let reply = until(&mut stdout, r#""request_seq":6"#);
+    assert!(reply.contains(r#""name":"locals","variablesReference":1"#), "{reply}");
+    send(&mut stdin, r#"{"seq":7,"type":"request","command":"variables","arguments":{"variablesReference":1}}"#);
+    let reply = until(&mut stdout, r#""request_seq":7"#);
+    assert!(reply.contains(r#"{"name":"result","value":"9","variablesReference":0}"#), "{reply}");
+
+    send(&mut stdin, r#"{"seq":8,"type":"request","command":"continue","arguments":{"threadId":1}}"#);
+    let output = until(&mut stdout, r#""event":"output""#);
+    assert!(output.contains(r#""category":"stdout","output":"9\n""#), "{output}");
+    let output = until(&mut stdout, r#""event":"output""#);
+    assert!(output.contains(r#""category":"stderr","output":"done\n""#), "{output}");
+    let exited = until(&mut stdout, r#""event":"exited""#);
+    assert!(exited.contains(r#""exitCode":0"#), "{exited}");
+    send(&mut stdin, r#"{"seq":9,"type":"request","command":"disconnect"}"#);
+    drop(stdin);
+
+    assert!(child.wait().unwrap().success());
+    std::fs::remove_file(&path).unwrap();
+}
+
+// Files are granted on the command line and by `launch`, which adds to what `rlox` was given.
+#[test]
+fn permissions() {
+    let dir = std::env::temp_dir().join(format!("rlox-dap-files-{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(dir.join("data")).unwrap();
+    std::fs::create_dir_all(dir.join("out")).unwrap();
+    std::fs::write(dir.join("data/in.txt"), "from data").unwrap();
+    let dir = dir.canonicalize().unwrap();
+    let script = format!(r#"writeFile("{0}/out/copy.txt", readFile("{0}/data/in.txt"));"#, dir.display());
+    std::fs::write(dir.join("script.lox"), script).unwrap();
+    let program = dir.join("script.lox").display().to_string();
+
+    let allow_read = format!("--allow-read={}", dir.join("data").display());
+    let (mut child, mut stdin, mut stdout) = adapter(&[&allow_read]);
+    send(&mut stdin, r#"{"seq":1,"type":"request","command":"initialize","arguments":{}}"#);
+    until(&mut stdout, r#""event":"initialized""#);
+    let out = dir.join("out").display().to_string();
+    send(&mut stdin, &format!(r#"{{"seq":2,"type":"request","command":"launch","arguments":{{"program":"{program}","allowWrite":["{out}"]}}}}"#));
+    let reply = until(&mut stdout, r#""request_seq":2"#);
+    assert!(reply.contains(r#""success":true"#), "{reply}");
+    send(&mut stdin, r#"{"seq":3,"type":"request","command":"configurationDone"}"#);
+    let exited = until(&mut stdout, r#""event":"exited""#);
+    assert!(exited.contains(r#""exitCode":0"#), "{exited}");
+    send(&mut stdin, r#"{"seq":4,"type":"request","command":"disconnect"}"#);
+    drop(stdin);
+
+    assert!(child.wait().unwrap().success());
+    assert_eq!(std::fs::read_to_string(dir.join("out/copy.txt")).unwrap(), "from data");
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+// Some clients finish configuring before they send `launch`; the program still runs.
+#[test]
+fn configuration_done_before_launch() {
+    let path = std::env::temp_dir().join(format!("rlox-dap-early-{}.lox", std::process::id()));
+    std::fs::write(&path, SCRIPT).unwrap();
+    let program = path.canonicalize().unwrap().display().to_string();
+
+    let (mut child, mut stdin, mut stdout) = adapter(&[]);
+    send(&mut stdin, r#"{"seq":1,"type":"request","command":"initialize","arguments":{}}"#);
+    until(&mut stdout, r#""event":"initialized""#);
+    send(&mut stdin, r#"{"seq":2,"type":"request","command":"configurationDone"}"#);
+    until(&mut stdout, r#""request_seq":2"#);
+    send(&mut stdin, &format!(r#"{{"seq":3,"type":"request","command":"launch","arguments":{{"program":"{program}"}}}}"#));
+    let output = until(&mut stdout, r#""event":"output""#);
+    assert!(output.contains(r#""category":"stdout","output":"9\n""#), "{output}");
+    let exited = until(&mut stdout, r#""event":"exited""#);
+    assert!(exited.contains(r#""exitCode":0"#), "{exited}");
+    send(&mut stdin, r#"{"seq":4,"type":"request","command":"disconnect"}"#);
+    drop(stdin);
+
+    assert!(child.wait().unwrap().success());
+    std::fs::remove_file(&path).unwrap();
+}
@@ -0,0 +1,63 @@
+use std::io::Write;
+use std::process::{Command, Output, Stdio};
+
+// Runs `rlox debug` on a script with `input` piped to it, for commands and the script alike.
+fn debug(name: &str, script: &str, input: &str) -> Output {
+    let path = std::env::temp_dir().join(format!("rlox-debugger-{name}-{}.lox", std::process::id()));
+    std::fs::write(&path, script).unwrap();
+    let mut child = Command::new(env!("CARGO_BIN_EXE_rlox"))
+        .arg("debug")
+        .arg(&path)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()
+        .unwrap();
+    child.stdin.take().unwrap().write_all(input.as_bytes()).unwrap();
+    let output = child.wait_with_output().unwrap();
+    std::fs::remove_file(&path).unwrap();
+    output
+}
+
+const SCRIPT: &str = "fun square(n) {
+  var result = n * n;
+  return result;
+}
+var total = 0;
+total = total + square(3);
+print total;
+";
+
+// Drives `rlox debug` from stdin: a breakpoint, the call stack and some variables, then on to the end.
+#[test]
+fn session() {
+    let output = debug("session", SCRIPT, "b 3\nc\nbt\np result\np n\np missing\nc\n");
+
+    assert!(output.status.success());
+    assert_eq!(String::from_utf8(output.stdout).unwrap(), "\
+Stopped at entry, line 1. Type 'help' for commands.
+->    1 | fun square(n) {
+(rlox) Breakpoint set at line 3.
+(rlox) Breakpoint at line 3.
+->    3 |   return result;
+(rlox) #0 square at line 3
+#1 <script> at line 6
+(rlox) result = 9
+(rlox) n = 3
+(rlox) Undefined variable 'missing'.
+(rlox) 9
+Program finished.
+");
+}
+
+// Commands and the script's own reads take turns on the same stdin, neither reading ahead.
+#[test]
+fn script_reads_between_commands() {
+    let output = debug("input", "var a = readLine();\nprint \"got \" + a;\n", "continue\nhello\n");
+    assert!(output.status.success());
+    assert_eq!(String::from_utf8(output.stdout).unwrap(), "\
+Stopped at entry, line 1. Type 'help' for commands.
+->    1 | var a = readLine();
+(rlox) got hello
+Program finished.
+");
+}
@@ -0,0 +1,49 @@
+use std::process::Command;
+
+const SCRIPT: &str = "fun add(a, b) { return a + b; }
+for (var i = 0; i < 2; i = i + 1) print add(i, 1);
+";
+
+fn dump(flag: &str) -> String {
+    let path = std::env::temp_dir().join(format!("rlox-dump{flag}-{}.lox", std::process::id()));
+    std::fs::write(&path, SCRIPT).unwrap();
+    let output = Command::new(env!("CARGO_BIN_EXE_rlox")).arg(flag).arg(&path).output().unwrap();
+    std::fs::remove_file(&path).unwrap();
+    assert!(output.status.success());
+    String::from_utf8(output.stdout).unwrap()
+}
+
+// Both formats print the same tree: the `for` loop as the block and `while` it is desugared into.
+#[test]
+fn both_formats() {
+    let sexpr = "(fun add(a b) (return (+ a b)))
+(block (var i = 0) (while (< i 2) (block (print (call add i 1)) (; (= i (+ i 1))))))
+";
+    let tree = "fun add(a b)
+  return
+    +
+      a
+      b
+block
+  var i =
+    0
+  while
+    <
+      i
+      2
+    block
+      print
+        call
+          add
+          i
+          1
+      ;
+        = i
+          +
+            i
+            1
+";
+    assert_eq!(dump("--dump-ast"), sexpr);
+    assert_eq!(dump("--dump-ast=sexpr"), sexpr);
+    assert_eq!(dump("--dump-ast=tree"), tree);
+}
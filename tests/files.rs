@@ -0,0 +1,82 @@
+use std::fs;
+use std::path::Path;
+use std::process::{Command, Output};
+
+fn rlox(dir: &Path, args: &[&str], script: &str) -> Output {
+    fs::write(dir.join("script.lox"), script).unwrap();
+    Command::new(env!("CARGO_BIN_EXE_rlox"))
+        .current_dir(dir)
+        .args(args)
+        .arg("script.lox")
+        .output()
+        .unwrap()
+}
+
+// Runs the file natives in a scratch directory, with and without the permissions they need.
+#[test]
+fn permissions() {
+    let dir = std::env::temp_dir().join(format!("rlox-files-{}", std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(dir.join("data")).unwrap();
+    fs::create_dir_all(dir.join("out")).unwrap();
+    fs::write(dir.join("data/in.txt"), "one\ntwo\n").unwrap();
+    fs::write(dir.join("secret.txt"), "key").unwrap();
+
+    let script = r#"
+        fun show(line) { print "[" + line + "]"; }
+        eachLine("data/in.txt", show);
+        listDir("data", show);
+        writeFile("out/a.txt", "hello");
+        appendFile("out/a.txt", " world");
+        print readFile("out/a.txt");
+        deleteFile("out/a.txt");
+        print fileExists("out/a.txt");
+    "#;
+    let output = rlox(&dir, &["--allow-read=data", "--allow-read=out", "--allow-write=out"], script);
+    assert_eq!(String::from_utf8(output.stdout).unwrap(), "[one]\n[two]\n[in.txt]\nhello world\nfalse\n");
+    assert!(output.status.success());
+
+    let output = rlox(&dir, &["--allow-read=data"], r#"writeFile("data/b.txt", "x");"#);
+    assert_eq!(output.status.code(), Some(70));
+    assert!(String::from_utf8(output.stderr).unwrap()
+        .contains("Function 'writeFile' is not allowed to write 'data/b.txt'; grant it with --allow-write."));
+    assert!(!dir.join("data/b.txt").exists());
+
+    let output = rlox(&dir, &["--allow-read=data"], r#"print readFile("data/../secret.txt");"#);
+    assert_eq!(output.status.code(), Some(70));
+    assert!(output.stdout.is_empty());
+
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+// A link whose target is missing must not let a write create that target.
+#[cfg(unix)]
+#[test]
+fn broken_links() {
+    let dir = std::env::temp_dir().join(format!("rlox-links-{}", std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(dir.join("out")).unwrap();
+    fs::create_dir_all(dir.join("outside")).unwrap();
+    std::os::unix::fs::symlink("../outside/pwned.txt", dir.join("out/link")).unwrap();
+
+    let output = rlox(&dir, &["--allow-write=out"], r#"writeFile("out/link", "escaped");"#);
+    assert_eq!(output.status.code(), Some(70));
+    assert!(String::from_utf8(output.stderr).unwrap().contains("Function 'writeFile' cannot follow the broken link 'out/link'."));
+    assert!(!dir.join("outside/pwned.txt").exists());
+    let output = rlox(&dir, &["--allow-write=out"], r#"appendFile("out/link", "escaped");"#);
+    assert_eq!(output.status.code(), Some(70));
+    assert!(!dir.join("outside/pwned.txt").exists());
+
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+// Commands that cannot hand permissions to a script refuse them rather than drop them.
+#[test]
+fn refused_where_unused() {
+    for command in [&["test", "example"][..], &["--dump-ast", "example/fib.lox"], &["fmt", "--check", "example"]] {
+        let output = Command::new(env!("CARGO_BIN_EXE_rlox")).arg("--allow-read=example").args(command).output().unwrap();
+        assert_eq!(output.status.code(), Some(64), "{command:?}");
+        assert!(String::from_utf8(output.stderr).unwrap().contains("does not take --allow-read or --allow-write."));
+        assert!(output.stdout.is_empty());
+    }
+}
@@ -0,0 +1,37 @@
+use std::fs;
+use std::path::Path;
+use std::process::{Command, Output};
+
+fn rlox(args: &[&Path]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_rlox")).args(args).output().unwrap()
+}
+
+// Formats every example that compiles, and checks the formatted script prints the same and
+// formats to itself.
+#[test]
+fn round_trip() {
+    let dir = std::env::temp_dir().join(format!("rlox-fmt-{}", std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+
+    let mut formatted = 0;
+    for entry in fs::read_dir("example").unwrap() {
+        let original = entry.unwrap().path();
+        let copy = dir.join(original.file_name().unwrap());
+        fs::copy(&original, &copy).unwrap();
+        if !rlox(&[Path::new("fmt"), &copy]).status.success() {
+            continue;
+        }
+        formatted += 1;
+
+        let (before, after) = (rlox(&[&original]), rlox(&[&copy]));
+        assert_eq!(before.stdout, after.stdout, "{} prints differently once formatted", original.display());
+        assert_eq!(before.status.code(), after.status.code(), "{} exits differently once formatted", original.display());
+        assert!(rlox(&[Path::new("fmt"), Path::new("--check"), &copy]).status.success(), "{} formats differently twice", original.display());
+    }
+    assert!(formatted > 20);
+    // Annotations are comments, so the formatted scripts must still pass as tests.
+    assert!(rlox(&[Path::new("test"), &dir]).status.success());
+
+    fs::remove_dir_all(&dir).unwrap();
+}
@@ -0,0 +1,48 @@
+use std::process::Command;
+
+// Runs the annotated scripts through `rlox test`, which checks them against their `// expect:` comments.
+#[test]
+fn examples() {
+    let output = Command::new(env!("CARGO_BIN_EXE_rlox"))
+        .args(["test", "example"])
+        .output()
+        .unwrap();
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(output.status.success(), "{stdout}");
+    assert!(!stdout.contains("FAIL") && stdout.ends_with(", failed 0.\n"), "{stdout}");
+}
+
+// Fixtures that fail on purpose, each in its own way, and one that passes despite a comment that
+// merely starts with "Error".
+#[test]
+fn failures() {
+    let dir = std::env::temp_dir().join(format!("rlox-golden-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let fixtures = [
+        ("a_pass.lox", "// Errors would be reported below.\nprint 1; // expect: 1\n"),
+        ("b_output.lox", "print 1; // expect: 2\nprint 3;\n"),
+        ("c_runtime.lox", "print 1; // expect runtime error: Boom.\n"),
+        ("d_compile.lox", "var = 1;\nprint 1; // Error at 'x': Expect Variable name.\n"),
+    ];
+    for (name, source) in fixtures {
+        std::fs::write(dir.join(name), source).unwrap();
+    }
+    let output = Command::new(env!("CARGO_BIN_EXE_rlox")).arg("test").arg(&dir).output().unwrap();
+    std::fs::remove_dir_all(&dir).unwrap();
+
+    assert_eq!(output.status.code(), Some(1));
+    let dir = dir.display();
+    assert_eq!(String::from_utf8(output.stdout).unwrap(), format!("\
+FAIL {dir}/b_output.lox
+     Expected output '2' on line 1 but got '1'.
+     Got output '3' when none was expected.
+FAIL {dir}/c_runtime.lox
+     Expected exit code 70 but got 0.
+     Missing expected runtime error '[line 1] Error: Boom.'.
+     Got output '1' when none was expected.
+FAIL {dir}/d_compile.lox
+     Missing expected error '[line 2] Error at 'x': Expect Variable name.'.
+     Unexpected error '[line 1] Error at '=': Expect Variable name.'.
+Passed 1 tests, failed 3.
+"));
+}
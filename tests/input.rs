@@ -0,0 +1,33 @@
+use std::io::Write;
+use std::process::{Command, Stdio};
+
+fn run(script: &str, input: &str) -> String {
+    let path = std::env::temp_dir().join(format!("rlox-input-{}.lox", std::process::id()));
+    std::fs::write(&path, script).unwrap();
+    let mut child = Command::new(env!("CARGO_BIN_EXE_rlox"))
+        .arg(&path)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()
+        .unwrap();
+    child.stdin.take().unwrap().write_all(input.as_bytes()).unwrap();
+    let output = child.wait_with_output().unwrap();
+    std::fs::remove_file(&path).unwrap();
+    assert!(output.status.success());
+    String::from_utf8(output.stdout).unwrap()
+}
+
+// Feeds standard input to a script through `input`, `readLine` and `readAll`.
+#[test]
+fn reading() {
+    let script = r#"
+        var name = input("Name? ");
+        print "Hello, " + name + "!";
+        print readLine();
+        print readAll();
+        print readLine();
+        print readAll() == "";
+    "#;
+    assert_eq!(run(script, "Ada\r\nsecond\nthe\nrest"), "Name? Hello, Ada!\nsecond\nthe\nrest\nnil\ntrue\n");
+    assert_eq!(run("print readLine();", ""), "nil\n");
+}
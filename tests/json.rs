@@ -0,0 +1,46 @@
+use std::process::Command;
+
+fn export(name: &str, script: &str) -> String {
+    let path = std::env::temp_dir().join(format!("rlox-json-{name}-{}.lox", std::process::id()));
+    std::fs::write(&path, script).unwrap();
+    let output = Command::new(env!("CARGO_BIN_EXE_rlox")).arg("--json").arg(&path).output().unwrap();
+    std::fs::remove_file(&path).unwrap();
+    assert!(output.status.success());
+    String::from_utf8(output.stdout).unwrap()
+}
+
+// Integer literals must come through exactly, even past the 53 bits a float holds.
+#[test]
+fn exact_integers() {
+    let stdout = export("integers", "print 9007199254740993 + 18446744073709551616 + 0.5;\n");
+    assert!(stdout.contains(r#""lexeme":"9007199254740993","literal":9007199254740993,"#), "{stdout}");
+    assert!(stdout.contains(r#""literal":"18446744073709551616","#), "{stdout}");
+    assert!(stdout.contains(r#""lexeme":"0.5","literal":0.5,"#), "{stdout}");
+}
+
+// Token spans count columns in characters from 1, and `end` is the last character.
+#[test]
+fn token_columns() {
+    let stdout = export("tokens", "var s = \"héllo\"; print s;\n");
+    assert!(stdout.starts_with(r#"{"version":1,"tokens":[{"type":"Var","lexeme":"var","literal":null,"span":{"start":{"line":1,"column":1},"end":{"line":1,"column":3}}},"#), "{stdout}");
+    assert!(stdout.contains(r#""literal":"héllo","span":{"start":{"line":1,"column":9},"end":{"line":1,"column":15}}}"#), "{stdout}");
+    assert!(stdout.contains(r#"{"type":"Print","lexeme":"print","literal":null,"span":{"start":{"line":1,"column":18},"end":{"line":1,"column":22}}}"#), "{stdout}");
+}
+
+// Expressions carry the span of their source, and nodes made up while desugaring carry none.
+#[test]
+fn expression_spans() {
+    let stdout = export("spans", "print (1 + 2) * 3;\nfor (;;) {}\n");
+    assert!(stdout.contains(r#"{"kind":"Binary","span":{"start":{"line":1,"column":7},"end":{"line":1,"column":17}},"left":{"kind":"Grouping","span":{"start":{"line":1,"column":7},"end":{"line":1,"column":13}},"#), "{stdout}");
+    assert!(stdout.contains(r#"{"kind":"While","span":{"start":{"line":2,"column":1},"end":{"line":2,"column":11}},"condition":{"kind":"Literal","span":null,"value":true}"#), "{stdout}");
+}
+
+// Variables and assignments carry how many scopes out the resolver found them, `null` for globals.
+#[test]
+fn resolver_depths() {
+    let stdout = export("depths", "var a = 1;\nfun f(b) {\n  {\n    b = a + b;\n  }\n}\n");
+    assert!(stdout.contains(r#""name":{"type":"Identifier","lexeme":"a","literal":null,"span":{"start":{"line":4,"column":9},"end":{"line":4,"column":9}}},"depth":null}"#), "{stdout}");
+    assert!(stdout.contains(r#""name":{"type":"Identifier","lexeme":"b","literal":null,"span":{"start":{"line":4,"column":13},"end":{"line":4,"column":13}}},"depth":1}"#), "{stdout}");
+    assert!(stdout.contains(r#"{"kind":"Assign","span":{"start":{"line":4,"column":5},"end":{"line":4,"column":13}},"#), "{stdout}");
+    assert!(stdout.contains(r#""depth":1}},"depth":1}}]}]}]}"#), "{stdout}");     // the assignment to `b`, last in the file
+}
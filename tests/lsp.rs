@@ -0,0 +1,35 @@
+use std::io::Write;
+use std::process::{Command, Stdio};
+
+fn frame(body: &str) -> String {
+    format!("Content-Length: {}\r\n\r\n{body}", body.len())
+}
+
+// Drives a whole session through `rlox lsp` and checks the replies it sends back.
+#[test]
+fn session() {
+    let open = r#"{"jsonrpc":"2.0","method":"textDocument/didOpen","params":{"textDocument":{"uri":"file:///a.lox","text":"var a = 1;\nprint a;\nvar = 2;\n"}}}"#;
+    let definition = r#"{"jsonrpc":"2.0","id":2,"method":"textDocument/definition","params":{"textDocument":{"uri":"file:///a.lox"},"position":{"line":1,"character":6}}}"#;
+    let input: String = [
+        r#"{"jsonrpc":"2.0","id":1,"method":"initialize","params":{}}"#,
+        open,
+        definition,
+        r#"{"jsonrpc":"2.0","id":3,"method":"shutdown"}"#,
+        r#"{"jsonrpc":"2.0","method":"exit"}"#,
+    ].into_iter().map(frame).collect();
+
+    let mut child = Command::new(env!("CARGO_BIN_EXE_rlox"))
+        .arg("lsp")
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()
+        .unwrap();
+    child.stdin.take().unwrap().write_all(input.as_bytes()).unwrap();
+    let output = child.wait_with_output().unwrap();
+    let stdout = String::from_utf8(output.stdout).unwrap();
+
+    assert!(output.status.success());
+    assert!(stdout.contains(r#""definitionProvider":true"#));
+    assert!(stdout.contains(r#""diagnostics":[{"range":{"start":{"line":2,"character":4},"end":{"line":2,"character":5}},"severity":1,"source":"rlox","message":"Expect Variable name."}]"#));
+    assert!(stdout.contains(r#""id":2,"result":{"uri":"file:///a.lox","range":{"start":{"line":0,"character":4},"end":{"line":0,"character":5}}}"#));
+}
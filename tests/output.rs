@@ -0,0 +1,39 @@
+use std::process::{Command, Output};
+
+fn run(script: &str) -> Output {
+    let path = std::env::temp_dir().join(format!("rlox-output-{}.lox", std::process::id()));
+    std::fs::write(&path, script).unwrap();
+    let output = Command::new(env!("CARGO_BIN_EXE_rlox")).arg(&path).output().unwrap();
+    std::fs::remove_file(&path).unwrap();
+    output
+}
+
+// Output written to a pipe is buffered, and must still all arrive, in order, however the script ends.
+#[test]
+fn streams() {
+    let output = run(r#"write("a"); print "b"; eprint("warning"); write("c");"#);
+    assert!(output.status.success());
+    assert_eq!(String::from_utf8(output.stdout).unwrap(), "ab\nc");
+    assert_eq!(String::from_utf8(output.stderr).unwrap(), "warning\n");
+
+    let output = run(r#"var i = 0; while (i < 10000) { print i; i = i + 1; } write("end"); nil();"#);
+    assert_eq!(output.status.code(), Some(70));
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(stdout.starts_with("0\n1\n") && stdout.ends_with("9999\nend"));
+}
+
+// With both streams in one file, as `2>&1` does, a runtime error comes after the output before it.
+#[test]
+fn error_after_output() {
+    let path = std::env::temp_dir().join(format!("rlox-output-error-{}.lox", std::process::id()));
+    let log = path.with_extension("log");
+    std::fs::write(&path, "print \"before\";\nwrite(\"partial\");\nnil();\n").unwrap();
+    let file = std::fs::File::create(&log).unwrap();
+    let status = Command::new(env!("CARGO_BIN_EXE_rlox")).arg(&path).stdout(file.try_clone().unwrap()).stderr(file).status().unwrap();
+    let text = std::fs::read_to_string(&log).unwrap();
+    std::fs::remove_file(&path).unwrap();
+    std::fs::remove_file(&log).unwrap();
+
+    assert_eq!(status.code(), Some(70));
+    assert_eq!(text, "before\npartial[line 3] Error: Can only call functions and classes.\n");
+}
@@ -0,0 +1,60 @@
+use std::process::Command;
+
+// `square` loops so that it takes long enough to show in the folded stacks, which leave out
+// stacks that took less than a microsecond.
+const SCRIPT: &str = "fun square(n) {
+  var k = 0;
+  while (k < 1000) { k = k + 1; }
+  return n * n;
+}
+var i = 0;
+while (i < 3) {
+  if (i == 1) print square(i); else print i;
+  i = i + 1;
+}
+";
+
+// Timings differ from run to run, and the tables are sorted by them, so they are masked and the
+// lines compared in sorted order.
+fn masked(text: &str) -> Vec<String> {
+    let mut lines: Vec<String> = text.lines().map(|line| {
+        let words: Vec<&str> = line.split_whitespace().map(|word| match word.contains('.') && word.parse::<f64>().is_ok() {
+            true => "#",
+            false => word,
+        }).collect();
+        words.join(" ")
+    }).collect();
+    lines.sort();
+    lines
+}
+
+// `--profile` reports calls and hits per function and line, and writes folded stacks for flame graphs.
+#[test]
+fn report() {
+    let dir = std::env::temp_dir().join(format!("rlox-profile-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    std::fs::write(dir.join("script.lox"), SCRIPT).unwrap();
+    let output = Command::new(env!("CARGO_BIN_EXE_rlox")).arg("--profile=out.folded").arg("script.lox").current_dir(&dir).output().unwrap();
+    let folded = std::fs::read_to_string(dir.join("out.folded")).unwrap();
+    std::fs::remove_dir_all(&dir).unwrap();
+
+    assert!(output.status.success());
+    assert_eq!(String::from_utf8(output.stdout).unwrap(), "0\n1\n2\n");
+    assert_eq!(masked(&String::from_utf8(output.stderr).unwrap()), masked("\
+function                            calls     total ms      self ms
+square:1                                1        6.544        6.544
+<script>                                1        6.749        0.205
+
+line                                 hits      self ms
+1                                       1        0.064
+2                                       1        0.004
+3                                    1001        6.523
+4                                       1        0.008
+6                                       1        0.009
+7                                       1        0.011
+8                                       6        0.087
+9                                       3        0.033
+"));
+    let stacks: Vec<&str> = folded.lines().map(|line| line.rsplit_once(' ').unwrap().0).collect();
+    assert_eq!(stacks, ["<script>", "<script>;square:1"]);
+}
@@ -0,0 +1,60 @@
+use std::process::{Command, Output};
+
+const SCRIPT: &str = "fun square(n) {
+  return n * n;
+}
+var i = 0;
+while (i < 2) {
+  print square(i);
+  i = i + 1;
+}
+";
+
+fn trace(dir: &std::path::Path, arguments: &[&str]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_rlox")).args(arguments).arg("script.lox").current_dir(dir).output().unwrap()
+}
+
+// `--trace` logs statements, calls, returns and assignments between the lines the script prints;
+// `--trace=square` keeps to that function, and `-o` sends the log to a file.
+#[test]
+fn log() {
+    let dir = std::env::temp_dir().join(format!("rlox-trace-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    std::fs::write(dir.join("script.lox"), SCRIPT).unwrap();
+    let everything = trace(&dir, &["--trace"]);
+    let only = trace(&dir, &["--trace=square", "-o", "trace.txt"]);
+    let log = std::fs::read_to_string(dir.join("trace.txt")).unwrap();
+    std::fs::remove_dir_all(&dir).unwrap();
+
+    assert!(everything.status.success());
+    assert_eq!(String::from_utf8(everything.stdout).unwrap(), "\
+[line 1] fun square(n) {
+[line 4] var i = 0;
+[line 5] while (i < 2) {
+[line 6] print square(i);
+[line 6] call square(0)
+  [line 2] return n * n;
+[line 2] return square -> 0
+0
+[line 7] i = i + 1;
+[line 7] assign i = 1 (global)
+[line 6] print square(i);
+[line 6] call square(1)
+  [line 2] return n * n;
+[line 2] return square -> 1
+1
+[line 7] i = i + 1;
+[line 7] assign i = 2 (global)
+");
+
+    assert!(only.status.success());
+    assert_eq!(String::from_utf8(only.stdout).unwrap(), "0\n1\n");
+    assert_eq!(log, "\
+[line 6] call square(0)
+  [line 2] return n * n;
+[line 2] return square -> 0
+[line 6] call square(1)
+  [line 2] return n * n;
+[line 2] return square -> 1
+");
+}